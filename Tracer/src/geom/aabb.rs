@@ -0,0 +1,117 @@
+use std::f64::INFINITY;
+use geom::vec3::Vec3;
+use geom::ray::Ray;
+
+/// AABB is an axis-aligned bounding box, described by its minimum and maximum corners.
+///
+/// Bounding boxes are cheap to intersect, so they are used to skip testing Rays against shapes
+/// they cannot possibly hit.
+#[derive(Debug, Clone, Copy)]
+pub struct AABB {
+    pub min: Vec3,
+    pub max: Vec3
+}
+
+impl AABB {
+    /// Constructs a bounding box from its minimum and maximum corners.
+    pub fn new(min: Vec3, max: Vec3) -> AABB {
+        AABB {min: min, max: max}
+    }
+
+    /// Constructs an "inside out" bounding box which contains nothing. Growing it by any point or
+    /// box results in exactly that point or box.
+    pub fn empty() -> AABB {
+        AABB {min: Vec3::new(INFINITY, INFINITY, INFINITY),
+              max: Vec3::new(-INFINITY, -INFINITY, -INFINITY)}
+    }
+
+    /// Constructs the smallest bounding box containing all of the points.
+    pub fn from_points(points: &[Vec3]) -> AABB {
+        points.iter().fold(AABB::empty(), |aabb, &point| aabb.grow(point))
+    }
+
+    /// Returns the smallest bounding box containing both this box and the point.
+    pub fn grow(&self, point: Vec3) -> AABB {
+        AABB {min: Vec3::new(self.min.x.min(point.x), self.min.y.min(point.y),
+                             self.min.z.min(point.z)),
+              max: Vec3::new(self.max.x.max(point.x), self.max.y.max(point.y),
+                             self.max.z.max(point.z))}
+    }
+
+    /// Returns the smallest bounding box containing both boxes.
+    pub fn union(&self, other: &AABB) -> AABB {
+        AABB {min: Vec3::new(self.min.x.min(other.min.x), self.min.y.min(other.min.y),
+                             self.min.z.min(other.min.z)),
+              max: Vec3::new(self.max.x.max(other.max.x), self.max.y.max(other.max.y),
+                             self.max.z.max(other.max.z))}
+    }
+
+    /// Returns the box where both boxes overlap. If they don't overlap at all, the result is
+    /// empty.
+    pub fn overlap(&self, other: &AABB) -> AABB {
+        AABB {min: Vec3::new(self.min.x.max(other.min.x), self.min.y.max(other.min.y),
+                             self.min.z.max(other.min.z)),
+              max: Vec3::new(self.max.x.min(other.max.x), self.max.y.min(other.max.y),
+                             self.max.z.min(other.max.z))}
+    }
+
+    /// Returns the point in the middle of the box.
+    pub fn centroid(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    /// Returns the total area of the six faces of the box, or 0 if the box is empty.
+    pub fn surface_area(&self) -> f64 {
+        let d = self.max - self.min;
+
+        if d.x < 0.0 || d.y < 0.0 || d.z < 0.0 {
+            0.0
+        } else {
+            2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+        }
+    }
+
+    /// Returns the axis (0 = x, 1 = y, 2 = z) along which the box is the longest.
+    pub fn longest_axis(&self) -> usize {
+        let d = self.max - self.min;
+
+        if d.x >= d.y && d.x >= d.z {
+            0
+        } else if d.y >= d.z {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Returns the distance along the Ray at which it enters the box, or None if the Ray misses
+    /// the box or would only reach it further away than max_distance. A Ray starting inside the
+    /// box enters it at distance 0.
+    pub fn intersect(&self, ray: &Ray, max_distance: f64) -> Option<f64> {
+        let mut t_min = 0.0;
+        let mut t_max = max_distance;
+
+        // The "slab" method: clip the Ray's extent against each pair of parallel planes.
+        for axis in 0..3 {
+            let inv_d = 1.0 / ray.direction[axis];
+            let mut t0 = (self.min[axis] - ray.origin[axis]) * inv_d;
+            let mut t1 = (self.max[axis] - ray.origin[axis]) * inv_d;
+
+            if inv_d < 0.0 {
+                let swap = t0;
+                t0 = t1;
+                t1 = swap;
+            }
+
+            // Written so NaNs (a Ray lying exactly in a slab plane) leave the bounds untouched.
+            t_min = if t0 > t_min { t0 } else { t_min };
+            t_max = if t1 < t_max { t1 } else { t_max };
+
+            if t_min > t_max {
+                return None;
+            }
+        }
+
+        Some(t_min)
+    }
+}
@@ -90,4 +90,25 @@ impl Color {
                g: if self.g <= 1.0 {self.g} else {1.0},
                b: if self.b <= 1.0 {self.b} else {1.0}}
     }
+
+    /// Returns the perceived brightness of a linear color (its Y component with Rec. 709
+    /// primaries).
+    pub fn luminance(&self) -> f64 {
+        (0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b) as f64
+    }
+
+    /// Returns a copy of a linear color encoded with the sRGB transfer curve, which is what
+    /// 8-bit image files and displays expect. Components are clamped to [0, 1] first.
+    pub fn to_srgb(self) -> Color {
+        fn encode(c: f32) -> f32 {
+            let c = c.max(0.0).min(1.0);
+            if c <= 0.0031308 {
+                12.92 * c
+            } else {
+                1.055 * c.powf(1.0 / 2.4) - 0.055
+            }
+        }
+
+        Color {r: encode(self.r), g: encode(self.g), b: encode(self.b)}
+    }
 }
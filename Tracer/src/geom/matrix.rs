@@ -0,0 +1,167 @@
+use std::ops::*;
+use std::fmt;
+use geom::vec3::Vec3;
+
+/// Matrix4 is a 4x4 matrix of reals, stored by rows, used for affine transformations of points
+/// and vectors in homogeneous coordinates.
+#[derive(Debug, Clone, Copy)]
+pub struct Matrix4 {
+    pub m: [[f64; 4]; 4]
+}
+
+impl Matrix4 {
+    /// Constructs a matrix from its rows.
+    pub fn new(m: [[f64; 4]; 4]) -> Matrix4 {
+        Matrix4 {m: m}
+    }
+
+    /// Constructs the identity matrix, which leaves everything unchanged.
+    pub fn identity() -> Matrix4 {
+        Matrix4::new([[1.0, 0.0, 0.0, 0.0],
+                      [0.0, 1.0, 0.0, 0.0],
+                      [0.0, 0.0, 1.0, 0.0],
+                      [0.0, 0.0, 0.0, 1.0]])
+    }
+
+    /// Constructs a matrix which moves points by offset.
+    pub fn translation(offset: Vec3) -> Matrix4 {
+        Matrix4::new([[1.0, 0.0, 0.0, offset.x],
+                      [0.0, 1.0, 0.0, offset.y],
+                      [0.0, 0.0, 1.0, offset.z],
+                      [0.0, 0.0, 0.0, 1.0]])
+    }
+
+    /// Constructs a matrix which scales each axis by the matching component of factors.
+    pub fn scale(factors: Vec3) -> Matrix4 {
+        Matrix4::new([[factors.x, 0.0, 0.0, 0.0],
+                      [0.0, factors.y, 0.0, 0.0],
+                      [0.0, 0.0, factors.z, 0.0],
+                      [0.0, 0.0, 0.0, 1.0]])
+    }
+
+    /// Constructs a matrix which rotates counter-clockwise by angle radians around an axis
+    /// through the origin.
+    pub fn rotation(axis: Vec3, angle: f64) -> Matrix4 {
+        let a = axis.norm();
+        let (s, c) = angle.sin_cos();
+        let t = 1.0 - c;
+
+        // Rodrigues' rotation formula, written out as a matrix.
+        Matrix4::new([[t * a.x * a.x + c, t * a.x * a.y - s * a.z, t * a.x * a.z + s * a.y, 0.0],
+                      [t * a.x * a.y + s * a.z, t * a.y * a.y + c, t * a.y * a.z - s * a.x, 0.0],
+                      [t * a.x * a.z - s * a.y, t * a.y * a.z + s * a.x, t * a.z * a.z + c, 0.0],
+                      [0.0, 0.0, 0.0, 1.0]])
+    }
+
+    /// Constructs a shearing matrix. Each parameter moves one coordinate in proportion to
+    /// another: xy is how much x moves in proportion to y, and so on.
+    pub fn shear(xy: f64, xz: f64, yx: f64, yz: f64, zx: f64, zy: f64) -> Matrix4 {
+        Matrix4::new([[1.0, xy, xz, 0.0],
+                      [yx, 1.0, yz, 0.0],
+                      [zx, zy, 1.0, 0.0],
+                      [0.0, 0.0, 0.0, 1.0]])
+    }
+
+    /// Returns the transpose of the matrix.
+    pub fn transpose(&self) -> Matrix4 {
+        let mut t = [[0.0; 4]; 4];
+
+        for row in 0..4 {
+            for col in 0..4 {
+                t[row][col] = self.m[col][row];
+            }
+        }
+
+        Matrix4::new(t)
+    }
+
+    /// Returns the inverse of the matrix, or None if it is singular.
+    pub fn inverse(&self) -> Option<Matrix4> {
+        // Gauss-Jordan elimination with partial pivoting on [self | identity].
+        let mut a = self.m;
+        let mut inv = Matrix4::identity().m;
+
+        for col in 0..4 {
+            let mut pivot = col;
+            for row in col + 1..4 {
+                if a[row][col].abs() > a[pivot][col].abs() {
+                    pivot = row;
+                }
+            }
+
+            if a[pivot][col].abs() < 1.0e-12 {
+                return None;
+            }
+
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+
+            let scale = 1.0 / a[col][col];
+            for k in 0..4 {
+                a[col][k] *= scale;
+                inv[col][k] *= scale;
+            }
+
+            for row in 0..4 {
+                if row != col {
+                    let factor = a[row][col];
+                    for k in 0..4 {
+                        a[row][k] -= factor * a[col][k];
+                        inv[row][k] -= factor * inv[col][k];
+                    }
+                }
+            }
+        }
+
+        Some(Matrix4::new(inv))
+    }
+
+    /// Transforms a point, which is affected by translation.
+    pub fn transform_point(&self, p: Vec3) -> Vec3 {
+        let m = &self.m;
+        let x = m[0][0] * p.x + m[0][1] * p.y + m[0][2] * p.z + m[0][3];
+        let y = m[1][0] * p.x + m[1][1] * p.y + m[1][2] * p.z + m[1][3];
+        let z = m[2][0] * p.x + m[2][1] * p.y + m[2][2] * p.z + m[2][3];
+        let w = m[3][0] * p.x + m[3][1] * p.y + m[3][2] * p.z + m[3][3];
+
+        if w == 1.0 { Vec3::new(x, y, z) } else { Vec3::new(x, y, z) / w }
+    }
+
+    /// Transforms a direction vector, which is not affected by translation.
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+                  m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+                  m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z)
+    }
+}
+
+/// Prepares the matrix for printing or stringifying, one row per line.
+impl fmt::Display for Matrix4 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in self.m.iter() {
+            write!(f, "[{}, {}, {}, {}]\n", row[0], row[1], row[2], row[3])?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the matrix product of two matrices. The result applies other first, then self.
+impl Mul for Matrix4 {
+    type Output = Matrix4;
+
+    fn mul(self, other: Matrix4) -> Matrix4 {
+        let mut product = [[0.0; 4]; 4];
+
+        for row in 0..4 {
+            for col in 0..4 {
+                for k in 0..4 {
+                    product[row][col] += self.m[row][k] * other.m[k][col];
+                }
+            }
+        }
+
+        Matrix4::new(product)
+    }
+}
@@ -1,7 +1,14 @@
 pub mod vec3;
 pub mod ray;
 pub mod color;
+pub mod aabb;
+pub mod matrix;
+pub mod transform;
+pub mod random;
 
 pub use geom::vec3::Vec3;
 pub use geom::ray::Ray;
 pub use geom::color::Color;
+pub use geom::aabb::AABB;
+pub use geom::matrix::Matrix4;
+pub use geom::transform::Transform;
@@ -0,0 +1,89 @@
+use std::cell::RefCell;
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+use ::rand::{Rng, SeedableRng, XorShiftRng, thread_rng};
+
+// Every thread gets its own generator, so sampling never has to wait on a lock. Unless it is
+// reseeded, each generator starts from a random seed.
+thread_local!(static GENERATOR: RefCell<XorShiftRng> = RefCell::new(thread_rng().gen()));
+
+/// Returns a random real number on [0, 1) from the current thread's generator.
+pub fn jitter() -> f64 {
+    GENERATOR.with(|generator| generator.borrow_mut().next_f64())
+}
+
+/// Picks a uniformly distributed random point on the unit disk.
+pub fn disk() -> (f64, f64) {
+    // Shirley and Chiu's concentric mapping of the square onto the disk, which keeps nearby
+    // points in the square nearby on the disk.
+    let a = 2.0 * jitter() - 1.0;
+    let b = 2.0 * jitter() - 1.0;
+
+    if a == 0.0 && b == 0.0 {
+        return (0.0, 0.0);
+    }
+
+    let (radius, angle) = if a.abs() > b.abs() {
+        (a, FRAC_PI_4 * (b / a))
+    } else {
+        (b, FRAC_PI_2 - FRAC_PI_4 * (a / b))
+    };
+
+    (radius * angle.cos(), radius * angle.sin())
+}
+
+/// Picks a uniformly distributed random point on a regular polygon with the given number of
+/// sides (at least 3), inscribed in the unit circle and rotated by rotation radians.
+pub fn polygon(sides: u32, rotation: f64) -> (f64, f64) {
+    // Pick one of the triangles between the center and an edge, then a point in it.
+    let side = ((jitter() * sides as f64) as u32).min(sides - 1);
+    let step = 2.0 * PI / sides as f64;
+    let (a0, a1) = (rotation + step * side as f64, rotation + step * (side + 1) as f64);
+
+    let mut u = jitter();
+    let mut v = jitter();
+    if u + v > 1.0 {
+        u = 1.0 - u;
+        v = 1.0 - v;
+    }
+
+    (u * a0.cos() + v * a1.cos(), u * a0.sin() + v * a1.sin())
+}
+
+/// Weighs a sample taken with probability density pdf against another way of sampling which
+/// would have picked it with density other_pdf (Veach's power heuristic). Weighing the samples
+/// of both ways like this and adding them up keeps each where it is the better guess.
+pub fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+
+    if a + b == 0.0 {
+        0.0
+    } else {
+        a / (a + b)
+    }
+}
+
+/// Reseeds the current thread's generator, so the numbers returned by jitter() from now on only
+/// depend on seed.
+pub fn seed(seed: u64) {
+    // Spread the seed's bits over the whole state with a couple of rounds of SplitMix64. An
+    // all-zero state would get the generator stuck, so the state is never allowed to be zero.
+    let a = split_mix(seed);
+    let b = split_mix(a);
+    let state = [a as u32, (a >> 32) as u32, b as u32, ((b >> 32) as u32) | 1];
+
+    GENERATOR.with(|generator| *generator.borrow_mut() = XorShiftRng::from_seed(state));
+}
+
+/// Combines a seed with an index (like a row or tile number), giving a new seed which is
+/// different for every index.
+pub fn derive_seed(seed: u64, index: u64) -> u64 {
+    split_mix(seed ^ split_mix(index))
+}
+
+/// One step of the SplitMix64 generator, which makes a good hash for 64-bit integers.
+fn split_mix(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,119 @@
+use geom::vec3::Vec3;
+use geom::ray::Ray;
+use geom::aabb::AABB;
+use geom::matrix::Matrix4;
+
+/// Transform is an invertible affine transformation. It keeps both the matrix and its inverse,
+/// since moving Rays into a shape's own space needs the inverse while moving the results back out
+/// needs the matrix itself.
+///
+/// Transforms are composed with then(), so that
+/// `Transform::scale(s).then(&Transform::rotation(axis, angle)).then(&Transform::translation(p))`
+/// first scales, then rotates, then translates.
+#[derive(Debug, Clone, Copy)]
+pub struct Transform {
+    matrix: Matrix4,
+    inverse: Matrix4
+}
+
+impl Transform {
+    /// Constructs a Transform from a matrix, or None if the matrix can't be inverted.
+    pub fn new(matrix: Matrix4) -> Option<Transform> {
+        matrix.inverse().map(|inverse| Transform {matrix: matrix, inverse: inverse})
+    }
+
+    /// Constructs the Transform which leaves everything unchanged.
+    pub fn identity() -> Transform {
+        Transform {matrix: Matrix4::identity(), inverse: Matrix4::identity()}
+    }
+
+    /// Constructs a Transform which moves things by offset.
+    pub fn translation(offset: Vec3) -> Transform {
+        Transform {matrix: Matrix4::translation(offset), inverse: Matrix4::translation(-offset)}
+    }
+
+    /// Constructs a Transform which scales each axis by the matching component of factors.
+    ///
+    /// Panics if any of the factors are 0, since the result would be flat and not invertible.
+    pub fn scale(factors: Vec3) -> Transform {
+        assert!(factors.x != 0.0 && factors.y != 0.0 && factors.z != 0.0,
+                "Cannot scale by 0!");
+
+        Transform {matrix: Matrix4::scale(factors),
+                   inverse: Matrix4::scale(Vec3::new(1.0 / factors.x, 1.0 / factors.y,
+                                                     1.0 / factors.z))}
+    }
+
+    /// Constructs a Transform which rotates counter-clockwise by angle radians around an axis
+    /// through the origin.
+    pub fn rotation(axis: Vec3, angle: f64) -> Transform {
+        let matrix = Matrix4::rotation(axis, angle);
+        // Rotation matrices are orthogonal, so the inverse is just the transpose.
+        Transform {matrix: matrix, inverse: matrix.transpose()}
+    }
+
+    /// Constructs a shearing Transform; see Matrix4::shear. Returns None for the (degenerate)
+    /// shears which flatten space.
+    pub fn shear(xy: f64, xz: f64, yx: f64, yz: f64, zx: f64, zy: f64) -> Option<Transform> {
+        Transform::new(Matrix4::shear(xy, xz, yx, yz, zx, zy))
+    }
+
+    /// Returns the Transform which applies this one, then other.
+    pub fn then(&self, other: &Transform) -> Transform {
+        Transform {matrix: other.matrix * self.matrix, inverse: self.inverse * other.inverse}
+    }
+
+    /// Returns the Transform which undoes this one.
+    pub fn inverse(&self) -> Transform {
+        Transform {matrix: self.inverse, inverse: self.matrix}
+    }
+
+    /// Gets the matrix of the Transform.
+    pub fn get_matrix(&self) -> Matrix4 {
+        self.matrix
+    }
+
+    /// Transforms a point.
+    pub fn point(&self, p: Vec3) -> Vec3 {
+        self.matrix.transform_point(p)
+    }
+
+    /// Transforms a direction vector, ignoring translation. The result is not normalized.
+    pub fn vector(&self, v: Vec3) -> Vec3 {
+        self.matrix.transform_vector(v)
+    }
+
+    /// Transforms a surface normal and normalizes it.
+    ///
+    /// Normals have to be transformed by the inverse transpose of the matrix, otherwise they
+    /// stop being perpendicular to the surface under non-uniform scaling and shearing.
+    pub fn normal(&self, n: Vec3) -> Vec3 {
+        self.inverse.transpose().transform_vector(n).norm()
+    }
+
+    /// Transforms a Ray. The new Ray's direction is normalized like every other Ray's, so
+    /// distances along it are not the same as distances along the original.
+    pub fn ray(&self, ray: &Ray) -> Ray {
+        Ray::new(self.point(ray.origin), self.vector(ray.direction))
+    }
+
+    /// Returns a box enclosing the transformed box.
+    pub fn aabb(&self, aabb: &AABB) -> AABB {
+        let mut result = AABB::empty();
+
+        for i in 0..8 {
+            let corner = Vec3::new(if i & 1 == 0 { aabb.min.x } else { aabb.max.x },
+                                   if i & 2 == 0 { aabb.min.y } else { aabb.max.y },
+                                   if i & 4 == 0 { aabb.min.z } else { aabb.max.z });
+            result = result.grow(self.point(corner));
+        }
+
+        result
+    }
+
+    /// Moves a Ray from the space this Transform maps into back to the space it maps from.
+    pub fn inverse_ray(&self, ray: &Ray) -> Ray {
+        Ray::new(self.inverse.transform_point(ray.origin),
+                 self.inverse.transform_vector(ray.direction))
+    }
+}
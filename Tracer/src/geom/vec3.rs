@@ -1,7 +1,7 @@
 use std::ops::*;
 use std::fmt;
 use std::f64::consts::PI;
-use ::rand::{Rng, thread_rng};
+use geom::random::jitter;
 
 /// Vec3 is the 3-dimensional vector struct that will handle
 /// most of the geometry in Lux.
@@ -63,6 +63,39 @@ impl Vec3 {
     pub fn project(&self, b: Vec3) -> Vec3 {
         b * ((*self * b) / (b * b))
     }
+
+    /// Returns two unit vectors perpendicular to this (normalized) vector and to each other, so
+    /// that (u, self, v) is an orthonormal basis with this vector pointing "up".
+    pub fn basis(&self) -> (Vec3, Vec3) {
+        // Any vector which isn't parallel to this one will do to start the basis from.
+        let helper = if self.x.abs() > 0.9 { Vec3::new(0.0, 0.0, 1.0) }
+                     else { Vec3::new(1.0, 0.0, 0.0) };
+        let u = (helper % *self).norm();
+        let v = u % *self;
+
+        (u, v)
+    }
+
+    /// Mirrors this vector about the normal norm, like a direction leaving the surface after a
+    /// perfect reflection of a direction pointing away from it.
+    pub fn reflect(&self, norm: Vec3) -> Vec3 {
+        norm * (2.0 * (*self * norm)) - *self
+    }
+
+    /// Refracts this (normalized) direction, which points away from the surface on norm's side,
+    /// through a surface with relative index of refraction eta (the index on norm's side over
+    /// the index on the other side). Returns None for total internal reflection.
+    pub fn refract(&self, norm: Vec3, eta: f64) -> Option<Vec3> {
+        let cos_i = *self * norm;
+        let sin2_t = eta * eta * (1.0 - cos_i * cos_i).max(0.0);
+
+        if sin2_t >= 1.0 {
+            return None;
+        }
+
+        let cos_t = (1.0 - sin2_t).sqrt();
+        Some(-*self * eta + norm * (eta * cos_i - cos_t))
+    }
 }
 
 /// Prepares the vector for printing or stringifying.
@@ -139,6 +172,20 @@ impl Rem for Vec3 {
     }
 }
 
+/// Returns a component of the vector by axis: 0 is x, 1 is y and 2 is z.
+impl Index<usize> for Vec3 {
+    type Output = f64;
+
+    fn index(&self, axis: usize) -> &f64 {
+        match axis {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Vec3 axis out of bounds!")
+        }
+    }
+}
+
 /// Returns a copy of the vector with negated components.
 impl Neg for Vec3 {
     type Output = Vec3;
@@ -147,10 +194,3 @@ impl Neg for Vec3 {
         Vec3 {x: -(self.x), y: -(self.y), z: -(self.z)}
     }
 }
-
-/// Returns a random real number on [0, 1]
-///
-/// I feel bad for copying code.
-fn jitter() -> f64 {
-    thread_rng().next_f64()
-}
@@ -0,0 +1,56 @@
+use std::error;
+use std::fmt;
+use std::io;
+
+/// ImageError is returned when an image can't be saved or loaded.
+#[derive(Debug)]
+pub enum ImageError {
+    /// The file couldn't be created, written to or read.
+    Io(io::Error),
+    /// The file's extension doesn't name any format Lux can write.
+    UnsupportedFormat(String),
+    /// The file's extension doesn't name any format Lux can read.
+    UnreadableFormat(String),
+    /// The file being loaded isn't a valid image of its format.
+    Malformed(String)
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ImageError::Io(ref err) => write!(f, "{}", err),
+            ImageError::UnsupportedFormat(ref extension) => {
+                write!(f, "unsupported image format \".{}\" (expected .ppm, .png, .pfm, .hdr \
+                           or .exr)", extension)
+            },
+            ImageError::UnreadableFormat(ref extension) => {
+                write!(f, "cannot read \".{}\" images (expected .pfm or .hdr)", extension)
+            },
+            ImageError::Malformed(ref message) => write!(f, "invalid image: {}", message)
+        }
+    }
+}
+
+impl error::Error for ImageError {
+    fn description(&self) -> &str {
+        match *self {
+            ImageError::Io(_) => "image I/O failed",
+            ImageError::UnsupportedFormat(_) => "unsupported image format",
+            ImageError::UnreadableFormat(_) => "unreadable image format",
+            ImageError::Malformed(_) => "invalid image"
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            ImageError::Io(ref err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for ImageError {
+    fn from(err: io::Error) -> ImageError {
+        ImageError::Io(err)
+    }
+}
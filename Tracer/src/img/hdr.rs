@@ -0,0 +1,390 @@
+use std::io;
+use std::io::prelude::*;
+
+use geom::color::Color;
+use img::error::ImageError;
+
+/// Runs shorter than this aren't worth run-length encoding in Radiance files.
+const MIN_RUN: usize = 4;
+
+/// Writes pixels (stored as pixels[x][y]) to out as a little-endian binary PFM (portable float
+/// map), which keeps every color as an unclamped 32-bit float.
+pub fn write_pfm<W: Write>(out: &mut W, width: usize, height: usize, pixels: &[Vec<Color>])
+                           -> Result<(), ImageError> {
+    // A negative scale means the data is little-endian.
+    write!(out, "PF\n{} {}\n-1.0\n", width, height)?;
+
+    let mut row = Vec::with_capacity(width * 12);
+
+    // PFM stores its rows from the bottom of the image to the top.
+    for y in (0..height).rev() {
+        row.clear();
+        for x in 0..width {
+            let color = pixels[x][y];
+            for &c in [color.r, color.g, color.b].iter() {
+                row.extend_from_slice(&le32(c.to_bits()));
+            }
+        }
+        out.write_all(&row)?;
+    }
+
+    Ok(())
+}
+
+/// Writes pixels (stored as pixels[x][y]) to out as a Radiance HDR (RGBE) image.
+///
+/// Each pixel is stored as three 8-bit mantissas sharing an 8-bit exponent, so colors keep about
+/// 1% precision over a huge range. Scanlines are run-length encoded the way Radiance does it,
+/// with each of the four components encoded separately.
+pub fn write_hdr<W: Write>(out: &mut W, width: usize, height: usize, pixels: &[Vec<Color>])
+                           -> Result<(), ImageError> {
+    write!(out, "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n", height, width)?;
+
+    let mut scanline = Vec::with_capacity(width * 4);
+    let mut component = Vec::with_capacity(width);
+
+    for y in 0..height {
+        scanline.clear();
+        for x in 0..width {
+            scanline.extend_from_slice(&to_rgbe(pixels[x][y]));
+        }
+
+        // Run-length encoded scanlines must be between 8 and 32767 pixels wide.
+        if width < 8 || width > 0x7FFF {
+            out.write_all(&scanline)?;
+            continue;
+        }
+
+        out.write_all(&[2, 2, (width >> 8) as u8, width as u8])?;
+
+        for i in 0..4 {
+            component.clear();
+            component.extend(scanline.iter().skip(i).step_by(4));
+            write_rle(out, &component)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes pixels (stored as pixels[x][y]) to out as an uncompressed scanline OpenEXR image with
+/// 32-bit float R, G and B channels.
+pub fn write_exr<W: Write>(out: &mut W, width: usize, height: usize, pixels: &[Vec<Color>])
+                           -> Result<(), ImageError> {
+    let mut header = Vec::new();
+
+    // Magic number, then version 2 with no flags set (a single-part scanline image).
+    header.extend_from_slice(&[0x76, 0x2F, 0x31, 0x01, 2, 0, 0, 0]);
+
+    // Channels have to be listed in alphabetical order.
+    let mut channels = Vec::new();
+    for name in [b'B', b'G', b'R'].iter() {
+        channels.push(*name);
+        channels.push(0);
+        channels.extend_from_slice(&le32(2)); // pixel type: FLOAT
+        channels.extend_from_slice(&[0, 0, 0, 0]); // pLinear and reserved bytes
+        channels.extend_from_slice(&le32(1)); // x sampling
+        channels.extend_from_slice(&le32(1)); // y sampling
+    }
+    channels.push(0);
+
+    let mut window = Vec::new();
+    for &value in [0, 0, width as u32 - 1, height as u32 - 1].iter() {
+        window.extend_from_slice(&le32(value));
+    }
+
+    write_attribute(&mut header, "channels", "chlist", &channels);
+    write_attribute(&mut header, "compression", "compression", &[0]); // NO_COMPRESSION
+    write_attribute(&mut header, "dataWindow", "box2i", &window);
+    write_attribute(&mut header, "displayWindow", "box2i", &window);
+    write_attribute(&mut header, "lineOrder", "lineOrder", &[0]); // INCREASING_Y
+    write_attribute(&mut header, "pixelAspectRatio", "float", &le32(1f32.to_bits()));
+    write_attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    write_attribute(&mut header, "screenWindowWidth", "float", &le32(1f32.to_bits()));
+    header.push(0);
+
+    out.write_all(&header)?;
+
+    // The header is followed by a table with the file offset of every scanline. Each scanline
+    // is its y coordinate, its size and then all of its B values, G values and R values.
+    let data_size = width * 3 * 4;
+    let table_size = height * 8;
+    for y in 0..height {
+        let offset = (header.len() + table_size + y * (8 + data_size)) as u64;
+        out.write_all(&le32(offset as u32))?;
+        out.write_all(&le32((offset >> 32) as u32))?;
+    }
+
+    let mut scanline = Vec::with_capacity(8 + data_size);
+    for y in 0..height {
+        scanline.clear();
+        scanline.extend_from_slice(&le32(y as u32));
+        scanline.extend_from_slice(&le32(data_size as u32));
+
+        for channel in 0..3 {
+            for x in 0..width {
+                let color = pixels[x][y];
+                let c = match channel {
+                    0 => color.b,
+                    1 => color.g,
+                    _ => color.r
+                };
+                scanline.extend_from_slice(&le32(c.to_bits()));
+            }
+        }
+
+        out.write_all(&scanline)?;
+    }
+
+    Ok(())
+}
+
+/// Reads a PFM (portable float map) image from input, in either byte order. Grayscale (Pf)
+/// maps are read as gray colors. Returns the width, height and pixels (stored as pixels[x][y]).
+pub fn read_pfm<R: BufRead>(input: &mut R) -> Result<(usize, usize, Vec<Vec<Color>>), ImageError> {
+    let channels = match &read_token(input)?[..] {
+        "PF" => 3,
+        "Pf" => 1,
+        _ => return Err(malformed("not a PFM file"))
+    };
+    let width = parse_size(&read_token(input)?)?;
+    let height = parse_size(&read_token(input)?)?;
+    let scale: f32 = read_token(input)?.parse().map_err(|_| malformed("invalid PFM scale"))?;
+
+    let mut pixels = vec![vec![Color::black(); height]; width];
+    let mut row = vec![0; width * channels * 4];
+
+    // PFM stores its rows from the bottom of the image to the top.
+    for y in (0..height).rev() {
+        input.read_exact(&mut row)?;
+
+        let values: Vec<f32> = row.chunks(4).map(|bytes| {
+            let bits = if scale < 0.0 {
+                (bytes[0] as u32) | (bytes[1] as u32) << 8 | (bytes[2] as u32) << 16
+                    | (bytes[3] as u32) << 24
+            } else {
+                (bytes[3] as u32) | (bytes[2] as u32) << 8 | (bytes[1] as u32) << 16
+                    | (bytes[0] as u32) << 24
+            };
+            f32::from_bits(bits)
+        }).collect();
+
+        for x in 0..width {
+            let pixel = &values[x * channels..(x + 1) * channels];
+            pixels[x][y] = if channels == 3 {
+                Color::new(pixel[0], pixel[1], pixel[2])
+            } else {
+                Color::new(pixel[0], pixel[0], pixel[0])
+            };
+        }
+    }
+
+    Ok((width, height, pixels))
+}
+
+/// Reads a Radiance HDR (RGBE) image from input, with either flat or run-length encoded
+/// scanlines. Only the standard orientation (-Y height +X width) is supported. Returns the
+/// width, height and pixels (stored as pixels[x][y]).
+pub fn read_hdr<R: BufRead>(input: &mut R) -> Result<(usize, usize, Vec<Vec<Color>>), ImageError> {
+    if !read_line(input)?.starts_with("#?") {
+        return Err(malformed("not a Radiance file"));
+    }
+
+    // The header is a list of variables, ended by an empty line.
+    loop {
+        let line = read_line(input)?;
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with("FORMAT=") && line != "FORMAT=32-bit_rle_rgbe" {
+            return Err(malformed("only RGBE Radiance files are supported"));
+        }
+    }
+
+    let resolution = read_line(input)?;
+    let parts: Vec<&str> = resolution.split_whitespace().collect();
+    if parts.len() != 4 || parts[0] != "-Y" || parts[2] != "+X" {
+        return Err(malformed("unsupported Radiance image orientation"));
+    }
+    let height = parse_size(parts[1])?;
+    let width = parse_size(parts[3])?;
+
+    let mut pixels = vec![vec![Color::black(); height]; width];
+    let mut scanline = vec![0; width * 4];
+
+    for y in 0..height {
+        let mut start = [0; 4];
+        input.read_exact(&mut start)?;
+
+        let encoded = width >= 8 && width <= 0x7FFF && start[0] == 2 && start[1] == 2
+                      && start[2] & 0x80 == 0;
+
+        if encoded {
+            if ((start[2] as usize) << 8 | start[3] as usize) != width {
+                return Err(malformed("wrong Radiance scanline length"));
+            }
+
+            // Each of the four components is run-length encoded separately.
+            for i in 0..4 {
+                let mut x = 0;
+                while x < width {
+                    let count = read_byte(input)? as usize;
+                    if count > 128 {
+                        let run = count - 128;
+                        if x + run > width {
+                            return Err(malformed("Radiance run overflows its scanline"));
+                        }
+                        let byte = read_byte(input)?;
+                        for _ in 0..run {
+                            scanline[x * 4 + i] = byte;
+                            x += 1;
+                        }
+                    } else {
+                        if count == 0 || x + count > width {
+                            return Err(malformed("invalid Radiance run"));
+                        }
+                        for _ in 0..count {
+                            scanline[x * 4 + i] = read_byte(input)?;
+                            x += 1;
+                        }
+                    }
+                }
+            }
+        } else {
+            scanline[..4].copy_from_slice(&start);
+            input.read_exact(&mut scanline[4..])?;
+        }
+
+        for x in 0..width {
+            pixels[x][y] = from_rgbe(&scanline[x * 4..x * 4 + 4]);
+        }
+    }
+
+    Ok((width, height, pixels))
+}
+
+/// Converts a color to Radiance's shared exponent format. Negative components are stored as 0.
+fn to_rgbe(color: Color) -> [u8; 4] {
+    let r = color.r.max(0.0) as f64;
+    let g = color.g.max(0.0) as f64;
+    let b = color.b.max(0.0) as f64;
+    let max = r.max(g).max(b);
+
+    if max < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+
+    // Find the exponent e with max = m * 2^e and m on [0.5, 1), so the largest component gets
+    // a mantissa of at least 128.
+    let mut exponent = max.log2().floor() as i32 + 1;
+    if max / 2f64.powi(exponent) >= 1.0 {
+        exponent += 1;
+    } else if max / 2f64.powi(exponent) < 0.5 {
+        exponent -= 1;
+    }
+
+    let exponent = exponent.max(-128).min(127);
+    let scale = 256.0 / 2f64.powi(exponent);
+
+    [(r * scale).min(255.0) as u8, (g * scale).min(255.0) as u8, (b * scale).min(255.0) as u8,
+     (exponent + 128) as u8]
+}
+
+/// Converts a pixel in Radiance's shared exponent format back to a color.
+fn from_rgbe(rgbe: &[u8]) -> Color {
+    if rgbe[3] == 0 {
+        return Color::black();
+    }
+
+    // Mantissas are rounded down when they're written, so add half a step back.
+    let scale = 2f32.powi(rgbe[3] as i32 - 136);
+    Color::new((rgbe[0] as f32 + 0.5) * scale, (rgbe[1] as f32 + 0.5) * scale,
+               (rgbe[2] as f32 + 0.5) * scale)
+}
+
+/// Run-length encodes one component of a Radiance scanline. Runs are written as 128 plus their
+/// length followed by the repeated byte, and anything else as its length followed by the bytes.
+fn write_rle<W: Write>(out: &mut W, data: &[u8]) -> io::Result<()> {
+    let mut i = 0;
+
+    while i < data.len() {
+        let run = run_length(data, i);
+        if run >= MIN_RUN {
+            out.write_all(&[128 + run as u8, data[i]])?;
+            i += run;
+            continue;
+        }
+
+        // Collect bytes until the next run worth encoding.
+        let start = i;
+        while i < data.len() && i - start < 128 && run_length(data, i) < MIN_RUN {
+            i += 1;
+        }
+
+        out.write_all(&[(i - start) as u8])?;
+        out.write_all(&data[start..i])?;
+    }
+
+    Ok(())
+}
+
+/// How many times the byte at start repeats, up to the longest run Radiance can encode (127).
+fn run_length(data: &[u8], start: usize) -> usize {
+    data[start..].iter().take(127).take_while(|&&byte| byte == data[start]).count()
+}
+
+fn write_attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&le32(value.len() as u32));
+    header.extend_from_slice(value);
+}
+
+fn le32(value: u32) -> [u8; 4] {
+    [value as u8, (value >> 8) as u8, (value >> 16) as u8, (value >> 24) as u8]
+}
+
+fn read_byte<R: BufRead>(input: &mut R) -> io::Result<u8> {
+    let mut byte = [0];
+    input.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+/// Reads a line of text, without its line ending.
+fn read_line<R: BufRead>(input: &mut R) -> Result<String, ImageError> {
+    let mut line = Vec::new();
+    if input.read_until(b'\n', &mut line)? == 0 {
+        return Err(malformed("unexpected end of file"));
+    }
+
+    Ok(String::from_utf8_lossy(&line).trim_end().to_string())
+}
+
+/// Reads a word of a PFM header, along with the single whitespace character which ends it.
+fn read_token<R: BufRead>(input: &mut R) -> Result<String, ImageError> {
+    let mut token = String::new();
+
+    loop {
+        let byte = read_byte(input)?;
+        if (byte as char).is_ascii_whitespace() {
+            if token.is_empty() {
+                continue;
+            }
+            return Ok(token);
+        }
+        token.push(byte as char);
+    }
+}
+
+fn parse_size(value: &str) -> Result<usize, ImageError> {
+    match value.parse() {
+        Ok(size) if size > 0 => Ok(size),
+        _ => Err(malformed("invalid image size"))
+    }
+}
+
+fn malformed(message: &str) -> ImageError {
+    ImageError::Malformed(message.to_string())
+}
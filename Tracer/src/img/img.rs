@@ -1,8 +1,56 @@
 use std::io::prelude::*;
+use std::io::{BufReader, BufWriter};
 use std::fs::File;
+use std::path::Path;
 use std::sync::Mutex;
 
 use geom::color::Color;
+use img::{hdr, png, ppm};
+use img::error::ImageError;
+use img::tonemap::SaveOptions;
+
+/// The file formats an Image can be saved as.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    /// Plain text portable pixmap, 8 bits per channel.
+    Ppm,
+    /// PNG, 8 or 16 bits per channel.
+    Png,
+    /// Portable float map, with 32-bit floats.
+    Pfm,
+    /// Radiance .hdr, with a shared exponent for each pixel.
+    Radiance,
+    /// Uncompressed OpenEXR, with 32-bit floats.
+    OpenExr
+}
+
+impl ImageFormat {
+    /// Picks a format from a file's extension: .ppm, .png, .pfm, .hdr or
+    /// .exr. Files without an extension are saved as a ppm.
+    pub fn from_filename(filename: &str) -> Result<ImageFormat, ImageError> {
+        let extension = match Path::new(filename).extension() {
+            Some(extension) => extension.to_string_lossy().to_lowercase(),
+            None => return Ok(ImageFormat::Ppm)
+        };
+
+        match &extension[..] {
+            "ppm" => Ok(ImageFormat::Ppm),
+            "png" => Ok(ImageFormat::Png),
+            "pfm" => Ok(ImageFormat::Pfm),
+            "hdr" => Ok(ImageFormat::Radiance),
+            "exr" => Ok(ImageFormat::OpenExr),
+            _ => Err(ImageError::UnsupportedFormat(extension))
+        }
+    }
+
+    /// Whether the format keeps colors outside of [0, 1].
+    pub fn is_hdr(&self) -> bool {
+        match *self {
+            ImageFormat::Pfm | ImageFormat::Radiance | ImageFormat::OpenExr => true,
+            ImageFormat::Ppm | ImageFormat::Png => false
+        }
+    }
+}
 
 // An Image struct stores a two-dimensional Vec of Color
 pub struct Image {
@@ -38,45 +86,125 @@ impl Image {
         Image {width: width, height: height, image_lock: Mutex::new(buffer)}
     }
 
+    /// Loads an image file, picking the format from the file extension. Only the high dynamic
+    /// range formats .pfm and .hdr can be read.
+    pub fn load(filename: &str) -> Result<Image, ImageError> {
+        let format = ImageFormat::from_filename(filename)?;
+        if format != ImageFormat::Pfm && format != ImageFormat::Radiance {
+            let extension = Path::new(filename).extension().map_or(String::new(), |extension| {
+                extension.to_string_lossy().to_lowercase()
+            });
+            return Err(ImageError::UnreadableFormat(extension));
+        }
+
+        let mut file = BufReader::new(File::open(filename)?);
+        let (width, height, pixels) = if format == ImageFormat::Pfm {
+            hdr::read_pfm(&mut file)?
+        } else {
+            hdr::read_hdr(&mut file)?
+        };
+
+        Ok(Image {width: width as i32, height: height as i32, image_lock: Mutex::new(pixels)})
+    }
+
+    pub fn get_width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> i32 {
+        self.height
+    }
+
+    /// Returns the color at (x, y).
+    pub fn get_color(&self, x: i32, y: i32) -> Color {
+        assert!(x >= 0 && x < self.width, "x out of bounds!");
+        assert!(y >= 0 && y < self.height, "y out of bounds!");
+
+        match self.image_lock.lock() {
+            Ok(image) => image[x as usize][y as usize],
+            Err(poisoned) => poisoned.into_inner()[x as usize][y as usize]
+        }
+    }
+
     /// Replaces the color at (x, y) with the new color provided
     pub fn set_color(&mut self, x: i32, y: i32, color: Color) {
-        let image_guard = self.image_lock.lock();
+        assert!(x >= 0 && x < self.width, "x out of bounds!");
+        assert!(y >= 0 && y < self.height, "y out of bounds!");
+
+        // Nothing else can hold the lock while we have a mutable reference, so there's no need
+        // to lock it. A thread which panicked while holding it can't have left a pixel half
+        // written, so a poisoned image is still usable.
+        let image = match self.image_lock.get_mut() {
+            Ok(image) => image,
+            Err(poisoned) => poisoned.into_inner()
+        };
+
+        image[x as usize][y as usize] = color;
+    }
+
+    /// Save the image struct as an image file, picking the format from
+    /// the file extension (see ImageFormat::from_filename).
+    pub fn save(&self, filename: &str, options: &SaveOptions) -> Result<(), ImageError> {
+        self.save_as(filename, ImageFormat::from_filename(filename)?, options)
+    }
 
-        if let Ok(mut image) = image_guard {
+    /// Save the image struct as a ppm (portable pixmap) image file, whatever the extension of
+    /// filename is.
+    pub fn save_ppm(&self, filename: &str, options: &SaveOptions) -> Result<(), ImageError> {
+        self.save_as(filename, ImageFormat::Ppm, options)
+    }
 
-            assert!(x >= 0 && x < self.width, "x out of bounds!");
-            assert!(y >= 0 && y < self.height, "y out of bounds!");
+    /// Save the image struct as a PNG image file, with the bit depth and transfer curve given
+    /// by options.
+    pub fn save_png(&self, filename: &str, options: &SaveOptions) -> Result<(), ImageError> {
+        self.save_as(filename, ImageFormat::Png, options)
+    }
 
-            image[x as usize][y as usize] = color;
-        } //TODO: else panic?
+    /// Save the image struct as a high dynamic range image file in the given format, which
+    /// keeps colors outside of [0, 1]. Only the exposure of options is applied.
+    pub fn save_hdr(&self, filename: &str, format: ImageFormat, options: &SaveOptions)
+                    -> Result<(), ImageError> {
+        assert!(format.is_hdr(), "Cannot save a high dynamic range image as {:?}!", format);
+
+        self.save_as(filename, format, options)
     }
 
-    /// Save the image struct as a ppm (portable bitmap) image file.
+    /// Save the image struct as an image file in the given format.
     ///
-    /// The file is saved as the filename parameter, without the .ppm
-    /// file extension added onto the end.
-    pub fn save(&self, filename: &str) {
-        let image_guard = self.image_lock.lock();
-
-        if let Ok(image) = image_guard {
-            //TODO: using unwrap is bad.
-            //TODO: check for file extension?
-            let mut file = File::create(filename).unwrap();
-            write!(file, "P3\n").unwrap();
-            write!(file, "{} {}\n", self.width, self.height).unwrap();
-            write!(file, "255\n").unwrap();
-
-            for y in 0..self.height {
-                for x in 0..self.width {
-                    //the i32 value for x and y need to be cast to usize
-                    let color = image[x as usize][y as usize].clamp();
-                    let r = (color.r * 255.0) as i32;
-                    let g = (color.g * 255.0) as i32;
-                    let b = (color.b * 255.0) as i32;
-                    write!(file, "{} {} {} ", r, g, b).unwrap();
-                }
-                write!(file, "\n").unwrap();
+    /// The colors are run through the display transform described by
+    /// options first, or only exposed for high dynamic range formats.
+    pub fn save_as(&self, filename: &str, format: ImageFormat, options: &SaveOptions)
+                   -> Result<(), ImageError> {
+        // Like in set_color, a poisoned image still holds every pixel that was written.
+        let image = self.image_lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let transform = |color| {
+            if format.is_hdr() {
+                options.expose(color)
+            } else {
+                options.display(color)
             }
-        } //TODO: else panic?
+        };
+        let pixels: Vec<Vec<Color>> = image.iter()
+                                           .map(|column| column.iter()
+                                                               .map(|&c| transform(c))
+                                                               .collect())
+                                           .collect();
+        let (width, height) = (self.width as usize, self.height as usize);
+
+        let mut file = BufWriter::new(File::create(filename)?);
+
+        match format {
+            ImageFormat::Ppm => ppm::write_ppm(&mut file, width, height, &pixels)?,
+            ImageFormat::Png => png::write_png(&mut file, width, height, &pixels,
+                                               options.sixteen_bit, options.transfer)?,
+            ImageFormat::Pfm => hdr::write_pfm(&mut file, width, height, &pixels)?,
+            ImageFormat::Radiance => hdr::write_hdr(&mut file, width, height, &pixels)?,
+            ImageFormat::OpenExr => hdr::write_exr(&mut file, width, height, &pixels)?
+        }
+
+        // Dropping a BufWriter ignores any error from writing out what's left in it.
+        file.flush()?;
+        Ok(())
     }
 }
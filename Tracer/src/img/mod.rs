@@ -1,3 +1,11 @@
 pub mod img;
+pub mod error;
+pub mod tonemap;
+pub mod ppm;
+pub mod png;
+pub mod hdr;
+mod zlib;
 
-pub use img::img::Image;
+pub use img::img::{Image, ImageFormat};
+pub use img::error::ImageError;
+pub use img::tonemap::{SaveOptions, ToneMap, TransferCurve};
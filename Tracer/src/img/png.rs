@@ -0,0 +1,156 @@
+use std::io;
+use std::io::prelude::*;
+
+use geom::color::Color;
+use img::error::ImageError;
+use img::tonemap::TransferCurve;
+use img::zlib;
+
+/// Writes pixels (stored as pixels[x][y]) to out as an 8 or 16-bit RGB PNG image. The pixels
+/// should already be encoded with the given transfer curve, with components on [0, 1]. The curve
+/// is recorded in the file so viewers know how to show it.
+///
+/// Each scanline is filtered with whichever of the five PNG filters gives the smallest sum of
+/// absolute differences, which is the usual heuristic for picking filters, and the filtered
+/// image is compressed with zlib.
+pub fn write_png<W: Write>(out: &mut W, width: usize, height: usize, pixels: &[Vec<Color>],
+                           sixteen_bit: bool, transfer: TransferCurve)
+                           -> Result<(), ImageError> {
+    let bytes_per_pixel = if sixteen_bit { 6 } else { 3 };
+    let stride = width * bytes_per_pixel;
+
+    let mut filtered = Vec::with_capacity((stride + 1) * height);
+    let mut previous = vec![0u8; stride];
+    let mut current = Vec::with_capacity(stride);
+
+    for y in 0..height {
+        current.clear();
+
+        for x in 0..width {
+            let color = pixels[x][y];
+
+            for &c in [color.r, color.g, color.b].iter() {
+                let c = c.max(0.0).min(1.0);
+                if sixteen_bit {
+                    let value = (c * 65535.0).round() as u16;
+                    current.push((value >> 8) as u8);
+                    current.push(value as u8);
+                } else {
+                    current.push((c * 255.0).round() as u8);
+                }
+            }
+        }
+
+        filter_scanline(&current, &previous, bytes_per_pixel, &mut filtered);
+        previous.clone_from(&current);
+    }
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&be32(width as u32));
+    header.extend_from_slice(&be32(height as u32));
+    header.push(if sixteen_bit { 16 } else { 8 }); // bit depth
+    header.push(2); // color type: RGB
+    header.push(0); // compression method: zlib
+    header.push(0); // filter method: adaptive
+    header.push(0); // no interlacing
+
+    out.write_all(&[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'])?;
+    write_chunk(out, b"IHDR", &header)?;
+
+    // gAMA stores the exponent the values were encoded with, times 100000.
+    match transfer {
+        // Rendering intent: perceptual.
+        TransferCurve::Srgb => write_chunk(out, b"sRGB", &[0])?,
+        TransferCurve::Linear => write_chunk(out, b"gAMA", &be32(100000))?,
+        TransferCurve::Gamma(gamma) => {
+            write_chunk(out, b"gAMA", &be32((100000.0 / gamma).round() as u32))?
+        }
+    }
+
+    write_chunk(out, b"IDAT", &zlib::compress(&filtered))?;
+    write_chunk(out, b"IEND", &[])?;
+    Ok(())
+}
+
+/// Appends one scanline to out, prefixed by the type of the filter used on it.
+fn filter_scanline(current: &[u8], previous: &[u8], bpp: usize, out: &mut Vec<u8>) {
+    let mut best_filter = 0;
+    let mut best_score = u64::max_value();
+
+    for filter in 0..5 {
+        let score: u64 = (0..current.len())
+            .map(|i| (filter_byte(filter, current, previous, bpp, i) as i8 as i16).abs() as u64)
+            .sum();
+
+        if score < best_score {
+            best_score = score;
+            best_filter = filter;
+        }
+    }
+
+    out.push(best_filter);
+    for i in 0..current.len() {
+        out.push(filter_byte(best_filter, current, previous, bpp, i));
+    }
+}
+
+/// Applies one of the PNG filters to the byte at index i of a scanline.
+fn filter_byte(filter: u8, current: &[u8], previous: &[u8], bpp: usize, i: usize) -> u8 {
+    let a = if i >= bpp { current[i - bpp] } else { 0 }; // left
+    let b = previous[i]; // above
+    let c = if i >= bpp { previous[i - bpp] } else { 0 }; // above left
+    let x = current[i];
+
+    match filter {
+        0 => x,
+        1 => x.wrapping_sub(a),
+        2 => x.wrapping_sub(b),
+        3 => x.wrapping_sub(((a as u16 + b as u16) / 2) as u8),
+        _ => x.wrapping_sub(paeth(a, b, c))
+    }
+}
+
+/// The Paeth predictor picks whichever of the left, above and above-left bytes is closest to
+/// left + above - above-left.
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// Writes a chunk: its length, type, data and the CRC of the type and data.
+fn write_chunk<W: Write>(out: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    out.write_all(&be32(data.len() as u32))?;
+    out.write_all(kind)?;
+    out.write_all(data)?;
+
+    let crc = crc32(kind.iter().chain(data.iter()));
+    out.write_all(&be32(crc))
+}
+
+fn be32(value: u32) -> [u8; 4] {
+    [(value >> 24) as u8, (value >> 16) as u8, (value >> 8) as u8, value as u8]
+}
+
+/// Computes the CRC-32 (as used by PNG and zlib) of some bytes.
+fn crc32<'a, I: Iterator<Item = &'a u8>>(bytes: I) -> u32 {
+    let mut crc = 0xFFFFFFFFu32;
+
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
+        }
+    }
+
+    !crc
+}
@@ -0,0 +1,26 @@
+use std::io::prelude::*;
+
+use geom::color::Color;
+use img::error::ImageError;
+
+/// Writes pixels (stored as pixels[x][y]) to out as a plain text ppm (portable pixmap) image.
+/// The pixels should already be encoded for display, with components on [0, 1].
+pub fn write_ppm<W: Write>(out: &mut W, width: usize, height: usize, pixels: &[Vec<Color>])
+                           -> Result<(), ImageError> {
+    write!(out, "P3\n")?;
+    write!(out, "{} {}\n", width, height)?;
+    write!(out, "255\n")?;
+
+    for y in 0..height {
+        for x in 0..width {
+            let color = pixels[x][y];
+            let r = (color.r.max(0.0).min(1.0) * 255.0).round() as i32;
+            let g = (color.g.max(0.0).min(1.0) * 255.0).round() as i32;
+            let b = (color.b.max(0.0).min(1.0) * 255.0).round() as i32;
+            write!(out, "{} {} {} ", r, g, b)?;
+        }
+        write!(out, "\n")?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use std::f64;
+use geom::color::Color;
+
+/// The curves which can squeeze the unbounded colors of a render into the [0, 1] range of a
+/// display.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToneMap {
+    /// Colors brighter than the white point are simply cut off.
+    Clamp,
+    /// Reinhard's operator, x / (1 + x), which never quite reaches white. Given a white point,
+    /// it is extended so that the white point maps to exactly 1.
+    Reinhard,
+    /// John Hable's filmic curve (from Uncharted 2), which has a toe that deepens the shadows
+    /// and a long shoulder for the highlights.
+    Filmic,
+    /// Krzysztof Narkowicz's fit of the ACES reference rendering transform.
+    Aces
+}
+
+impl ToneMap {
+    /// The white point used when none is given: where the curve itself reaches white.
+    pub fn default_white_point(&self) -> f64 {
+        match *self {
+            ToneMap::Clamp => 1.0,
+            ToneMap::Reinhard => f64::INFINITY,
+            // The linear white point from Hable's talk.
+            ToneMap::Filmic => 11.2,
+            // The fit already maps everything onto [0, 1].
+            ToneMap::Aces => f64::INFINITY
+        }
+    }
+}
+
+/// The transfer curves used to encode linear values for display.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransferCurve {
+    /// Values are stored as they are. Only useful for 16-bit images meant for more processing.
+    Linear,
+    /// The sRGB curve, which is what nearly every display and viewer expects.
+    Srgb,
+    /// A plain power curve, value^(1 / gamma).
+    Gamma(f64)
+}
+
+/// SaveOptions controls how an image's colors are turned into what ends up in a file.
+///
+/// For 8 and 16-bit formats, colors are scaled by the exposure, tone mapped and then encoded
+/// with the transfer curve. High dynamic range formats only get the exposure applied, so tone
+/// mapping can still be done later in compositing.
+#[derive(Debug, Clone, Copy)]
+pub struct SaveOptions {
+    /// Exposure adjustment in stops: each stop doubles the brightness of the image.
+    pub exposure: f64,
+    pub tone_map: ToneMap,
+    /// The (exposed) linear value which comes out as pure white, or None to use the tone
+    /// mapping curve's own.
+    pub white_point: Option<f64>,
+    pub transfer: TransferCurve,
+    /// Whether to write 16 bits per channel, for the formats which support it (PNG).
+    pub sixteen_bit: bool
+}
+
+impl Default for SaveOptions {
+    fn default() -> SaveOptions {
+        SaveOptions {exposure: 0.0, tone_map: ToneMap::Clamp, white_point: None,
+                     transfer: TransferCurve::Srgb, sixteen_bit: false}
+    }
+}
+
+impl SaveOptions {
+    /// Scales a color by the exposure.
+    pub fn expose(&self, color: Color) -> Color {
+        color * 2f64.powf(self.exposure)
+    }
+
+    /// Runs a color through the whole display transform: exposure, tone mapping and the
+    /// transfer curve. The returned color's components are on [0, 1].
+    pub fn display(&self, color: Color) -> Color {
+        let color = self.expose(color);
+        let mapped = Color::new(self.map(color.r), self.map(color.g), self.map(color.b));
+
+        match self.transfer {
+            TransferCurve::Linear => mapped,
+            TransferCurve::Srgb => mapped.to_srgb(),
+            TransferCurve::Gamma(gamma) => {
+                let encode = |c: f32| c.powf(1.0 / gamma as f32);
+                Color::new(encode(mapped.r), encode(mapped.g), encode(mapped.b))
+            }
+        }
+    }
+
+    /// Tone maps a single linear component onto [0, 1].
+    fn map(&self, c: f32) -> f32 {
+        let x = c.max(0.0) as f64;
+        let white = self.white_point.unwrap_or(self.tone_map.default_white_point()).max(1e-6);
+
+        let mapped = match self.tone_map {
+            ToneMap::Clamp => x / white,
+            ToneMap::Reinhard => x * (1.0 + x / (white * white)) / (1.0 + x),
+            ToneMap::Filmic => hable(x) / hable(white),
+            ToneMap::Aces if white.is_infinite() => aces(x),
+            ToneMap::Aces => aces(x) / aces(white)
+        };
+
+        mapped.max(0.0).min(1.0) as f32
+    }
+}
+
+/// The filmic curve from Hable's "Filmic Tonemapping Operators".
+fn hable(x: f64) -> f64 {
+    // Shoulder strength, linear strength, linear angle, toe strength, toe numerator and toe
+    // denominator.
+    let (a, b, c, d, e, f) = (0.15, 0.50, 0.10, 0.20, 0.02, 0.30);
+
+    ((x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f)) - e / f
+}
+
+/// Narkowicz's rational fit of the ACES filmic curve.
+fn aces(x: f64) -> f64 {
+    (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)
+}
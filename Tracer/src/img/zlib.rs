@@ -0,0 +1,452 @@
+/// The size of the window that LZ77 matches may reach back into.
+const WINDOW_SIZE: usize = 32768;
+
+/// The shortest and longest matches DEFLATE can encode.
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+
+/// How many earlier positions with the same hash are tried before settling on the best match
+/// found so far. Higher is slower, but compresses better.
+const MAX_CHAIN: usize = 64;
+
+const HASH_BITS: usize = 15;
+
+/// The first length of each DEFLATE length code (257 to 285) and how many extra bits follow it.
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43,
+                                51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4,
+                                4, 4, 5, 5, 5, 5, 0];
+
+/// The first distance of each DEFLATE distance code (0 to 29) and how many extra bits follow it.
+const DIST_BASE: [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385,
+                              513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385,
+                              24577];
+const DIST_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9,
+                              10, 10, 11, 11, 12, 12, 13, 13];
+
+/// The most bytes a stored DEFLATE block can hold.
+const MAX_STORED: usize = 65535;
+
+/// Compresses data into a zlib stream (RFC 1950) holding DEFLATE blocks (RFC 1951).
+///
+/// Repeated runs of bytes are found with a hash-chained LZ77 search and written as a single
+/// block with DEFLATE's fixed Huffman codes. This compresses rendered images well enough without
+/// the complexity of building dynamic Huffman tables. Data which that would only make bigger
+/// (like noise) is copied into stored blocks instead.
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    // CMF: DEFLATE with a 32K window. FLG: default compression, with the check bits making the
+    // header a multiple of 31.
+    let mut out = vec![0x78, 0x9C];
+
+    let compressed = deflate_fixed(data);
+    let blocks = (data.len() + MAX_STORED - 1) / MAX_STORED;
+    // Each stored block has a 5 byte header, and even empty data needs one block.
+    if compressed.len() <= data.len() + 5 * blocks.max(1) {
+        out.extend_from_slice(&compressed);
+    } else {
+        deflate_stored(&mut out, data);
+    }
+
+    let checksum = adler32(data);
+    out.extend_from_slice(&[(checksum >> 24) as u8, (checksum >> 16) as u8,
+                            (checksum >> 8) as u8, checksum as u8]);
+    out
+}
+
+/// Writes data as a single DEFLATE block using the fixed Huffman codes.
+fn deflate_fixed(data: &[u8]) -> Vec<u8> {
+    let mut out = BitWriter::new();
+
+    // BFINAL = 1, BTYPE = 01 (fixed Huffman codes).
+    out.write_bits(1, 1);
+    out.write_bits(1, 2);
+
+    let mut head = vec![usize::max_value(); 1 << HASH_BITS];
+    let mut prev = vec![usize::max_value(); WINDOW_SIZE];
+    let mut pos = 0;
+
+    while pos < data.len() {
+        let (length, distance) = longest_match(data, pos, &head, &prev);
+
+        if length >= MIN_MATCH {
+            write_length(&mut out, length);
+            write_distance(&mut out, distance);
+        } else {
+            write_literal(&mut out, data[pos] as u16);
+        }
+
+        let step = if length >= MIN_MATCH { length } else { 1 };
+        for p in pos..pos + step {
+            insert_hash(data, p, &mut head, &mut prev);
+        }
+        pos += step;
+    }
+
+    // End of block.
+    write_literal(&mut out, 256);
+    out.flush();
+    out.bytes
+}
+
+/// Appends data to out as uncompressed (stored) DEFLATE blocks.
+fn deflate_stored(out: &mut Vec<u8>, data: &[u8]) {
+    let mut chunks: Vec<&[u8]> = data.chunks(MAX_STORED).collect();
+    if chunks.is_empty() {
+        chunks.push(&[]);
+    }
+
+    let last = chunks.len() - 1;
+    for (i, chunk) in chunks.iter().enumerate() {
+        // BFINAL on the last block, and BTYPE = 00 (stored), padded to a whole byte. Every
+        // block before ends on a byte boundary, so the header starts a new byte.
+        out.push(if i == last { 1 } else { 0 });
+
+        let length = chunk.len() as u16;
+        out.extend_from_slice(&[length as u8, (length >> 8) as u8,
+                                !length as u8, (!length >> 8) as u8]);
+        out.extend_from_slice(chunk);
+    }
+}
+
+/// Computes the Adler-32 checksum that ends a zlib stream.
+pub fn adler32(data: &[u8]) -> u32 {
+    let mut a: u32 = 1;
+    let mut b: u32 = 0;
+
+    // 5552 is the most bytes that can be summed before b could overflow.
+    for chunk in data.chunks(5552) {
+        for &byte in chunk.iter() {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+
+    (b << 16) | a
+}
+
+fn hash(data: &[u8], pos: usize) -> usize {
+    let h = ((data[pos] as usize) << 10) ^ ((data[pos + 1] as usize) << 5)
+          ^ (data[pos + 2] as usize);
+    h & ((1 << HASH_BITS) - 1)
+}
+
+/// Records that the three bytes starting at pos can be found at pos.
+fn insert_hash(data: &[u8], pos: usize, head: &mut [usize], prev: &mut [usize]) {
+    if pos + MIN_MATCH > data.len() {
+        return;
+    }
+
+    let h = hash(data, pos);
+    prev[pos % WINDOW_SIZE] = head[h];
+    head[h] = pos;
+}
+
+/// Finds the longest earlier copy of the bytes starting at pos, returning its length and how far
+/// back it is. The length is 0 if there is no match.
+fn longest_match(data: &[u8], pos: usize, head: &[usize], prev: &[usize]) -> (usize, usize) {
+    if pos + MIN_MATCH > data.len() {
+        return (0, 0);
+    }
+
+    let max_length = MAX_MATCH.min(data.len() - pos);
+    let mut best = (0, 0);
+    let mut candidate = head[hash(data, pos)];
+    let mut tries = 0;
+
+    while candidate != usize::max_value() && pos - candidate <= WINDOW_SIZE
+          && tries < MAX_CHAIN {
+        let mut length = 0;
+        while length < max_length && data[candidate + length] == data[pos + length] {
+            length += 1;
+        }
+
+        if length > best.0 {
+            best = (length, pos - candidate);
+            if length == max_length {
+                break;
+            }
+        }
+
+        let next = prev[candidate % WINDOW_SIZE];
+        // Stop if the chain wraps around to a newer position, which means it was overwritten.
+        if next == usize::max_value() || next >= candidate {
+            break;
+        }
+        candidate = next;
+        tries += 1;
+    }
+
+    best
+}
+
+/// Writes a literal/length symbol with its fixed Huffman code.
+fn write_literal(out: &mut BitWriter, symbol: u16) {
+    let (code, bits) = if symbol < 144 {
+        (0x30 + symbol, 8)
+    } else if symbol < 256 {
+        (0x190 + symbol - 144, 9)
+    } else if symbol < 280 {
+        (symbol - 256, 7)
+    } else {
+        (0xC0 + symbol - 280, 8)
+    };
+
+    out.write_huffman(code as u32, bits);
+}
+
+fn write_length(out: &mut BitWriter, length: usize) {
+    let code = LENGTH_BASE.iter().rposition(|&base| base as usize <= length).unwrap();
+
+    write_literal(out, 257 + code as u16);
+    out.write_bits((length - LENGTH_BASE[code] as usize) as u32, LENGTH_EXTRA[code] as usize);
+}
+
+fn write_distance(out: &mut BitWriter, distance: usize) {
+    let code = DIST_BASE.iter().rposition(|&base| base as usize <= distance).unwrap();
+
+    // Fixed distance codes are simply the 5-bit code number.
+    out.write_huffman(code as u32, 5);
+    out.write_bits((distance - DIST_BASE[code] as usize) as u32, DIST_EXTRA[code] as usize);
+}
+
+/// BitWriter packs values into bytes starting from the least significant bit, as DEFLATE
+/// requires.
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    count: usize
+}
+
+impl BitWriter {
+    fn new() -> BitWriter {
+        BitWriter {bytes: Vec::new(), buffer: 0, count: 0}
+    }
+
+    /// Writes the lowest `bits` bits of value, least significant bit first.
+    fn write_bits(&mut self, value: u32, bits: usize) {
+        self.buffer |= value << self.count;
+        self.count += bits;
+
+        while self.count >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    /// Writes a Huffman code, which (unlike every other value) is packed most significant bit
+    /// first.
+    fn write_huffman(&mut self, code: u32, bits: usize) {
+        let mut reversed = 0;
+        for i in 0..bits {
+            reversed |= ((code >> i) & 1) << (bits - 1 - i);
+        }
+
+        self.write_bits(reversed, bits);
+    }
+
+    /// Pads the last partial byte with zeros.
+    fn flush(&mut self) {
+        if self.count > 0 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer = 0;
+            self.count = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads a DEFLATE stream's bits, least significant bit first.
+    struct BitReader<'a> {
+        bytes: &'a [u8],
+        pos: usize,
+        bit: usize
+    }
+
+    impl<'a> BitReader<'a> {
+        fn bits(&mut self, count: usize) -> usize {
+            let mut value = 0;
+            for i in 0..count {
+                let bit = (self.bytes[self.pos] >> self.bit) & 1;
+                value |= (bit as usize) << i;
+                self.bit += 1;
+                if self.bit == 8 {
+                    self.bit = 0;
+                    self.pos += 1;
+                }
+            }
+            value
+        }
+
+        /// Reads a Huffman code of the given length, which is packed most significant bit first.
+        fn huffman(&mut self, count: usize) -> usize {
+            (0..count).fold(0, |code, _| (code << 1) | self.bits(1))
+        }
+
+        fn align(&mut self) {
+            if self.bit > 0 {
+                self.bit = 0;
+                self.pos += 1;
+            }
+        }
+    }
+
+    /// Decodes a fixed Huffman literal/length symbol (RFC 1951, 3.2.6).
+    fn fixed_symbol(reader: &mut BitReader) -> usize {
+        let code = reader.huffman(7);
+        if code <= 0x17 {
+            return code + 256;
+        }
+
+        let code = (code << 1) | reader.bits(1);
+        if code >= 0x30 && code <= 0xBF {
+            code - 0x30
+        } else if code >= 0xC0 && code <= 0xC7 {
+            code - 0xC0 + 280
+        } else {
+            ((code << 1) | reader.bits(1)) - 0x190 + 144
+        }
+    }
+
+    /// A minimal zlib decoder which only understands stored and fixed Huffman blocks, returning
+    /// the data and which block types were seen.
+    fn inflate(stream: &[u8]) -> (Vec<u8>, Vec<usize>) {
+        assert_eq!(stream[0], 0x78);
+        assert_eq!(((stream[0] as usize) << 8 | stream[1] as usize) % 31, 0);
+
+        let mut reader = BitReader {bytes: stream, pos: 2, bit: 0};
+        let mut data = Vec::new();
+        let mut types = Vec::new();
+
+        loop {
+            let last = reader.bits(1) == 1;
+            let kind = reader.bits(2);
+            types.push(kind);
+
+            match kind {
+                0 => {
+                    reader.align();
+                    let length = reader.bits(16);
+                    assert_eq!(reader.bits(16), !length & 0xFFFF);
+                    data.extend_from_slice(&stream[reader.pos..reader.pos + length]);
+                    reader.pos += length;
+                },
+                1 => loop {
+                    let symbol = fixed_symbol(&mut reader);
+                    if symbol < 256 {
+                        data.push(symbol as u8);
+                        continue;
+                    } else if symbol == 256 {
+                        break;
+                    }
+
+                    let code = symbol - 257;
+                    let length = LENGTH_BASE[code] as usize
+                                 + reader.bits(LENGTH_EXTRA[code] as usize);
+                    let code = reader.huffman(5);
+                    let distance = DIST_BASE[code] as usize
+                                   + reader.bits(DIST_EXTRA[code] as usize);
+
+                    assert!(distance <= data.len() && distance <= WINDOW_SIZE);
+                    for _ in 0..length {
+                        let byte = data[data.len() - distance];
+                        data.push(byte);
+                    }
+                },
+                _ => panic!("unexpected block type {}", kind)
+            }
+
+            if last {
+                break;
+            }
+        }
+
+        reader.align();
+        let checksum = stream[reader.pos..].iter().fold(0, |sum, &byte| sum << 8 | byte as u32);
+        assert_eq!(reader.pos + 4, stream.len());
+        assert_eq!(checksum, adler32(&data));
+
+        (data, types)
+    }
+
+    /// Bytes from a small linear congruential generator, which don't compress.
+    fn noise(length: usize) -> Vec<u8> {
+        let mut state: u32 = 12345;
+        (0..length).map(|_| {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            (state >> 16) as u8
+        }).collect()
+    }
+
+    fn round_trip(data: &[u8]) -> Vec<usize> {
+        let (inflated, types) = inflate(&compress(data));
+        assert!(inflated == data, "Cannot get back what was compressed!");
+        types
+    }
+
+    #[test]
+    fn adler32_matches_known_checksums() {
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E60398);
+        // Enough 0xFF bytes to need the sums reduced partway through.
+        assert_eq!(adler32(&vec![0xFF; 100000]), 0x149A302C);
+    }
+
+    #[test]
+    fn round_trips_empty_data() {
+        round_trip(&[]);
+    }
+
+    #[test]
+    fn round_trips_text() {
+        let text = b"the quick brown fox jumps over the lazy dog, then the quick brown cat";
+        assert_eq!(round_trip(text), [1]);
+    }
+
+    #[test]
+    fn round_trips_long_repeats() {
+        // A run far longer than the longest match, and a pattern which only repeats further back
+        // than a match can be long.
+        let mut data = vec![7; 100000];
+        let pattern = noise(1000);
+        for _ in 0..40 {
+            data.extend_from_slice(&pattern);
+        }
+
+        let compressed = compress(&data);
+        assert!(compressed.len() < data.len() / 20, "Cannot compress repeats well!");
+        assert_eq!(round_trip(&data), [1]);
+    }
+
+    #[test]
+    fn round_trips_every_length_and_distance() {
+        let pattern = noise(WINDOW_SIZE);
+        let mut data = Vec::new();
+        for &distance in DIST_BASE.iter().chain([WINDOW_SIZE as u16 - 1].iter()) {
+            for &length in LENGTH_BASE.iter() {
+                let distance = distance as usize;
+                let start = data.len();
+                data.extend_from_slice(&pattern[..distance]);
+                for i in 0..length as usize {
+                    let byte = data[start + i % distance];
+                    data.push(byte);
+                }
+            }
+        }
+
+        round_trip(&data);
+    }
+
+    #[test]
+    fn stores_incompressible_data() {
+        // Noise gets larger with the fixed codes, so it's stored, in more than one block.
+        let data = noise(2 * MAX_STORED + 100);
+        assert_eq!(round_trip(&data), [0, 0, 0]);
+        assert_eq!(compress(&data).len(), data.len() + 3 * 5 + 6);
+    }
+}
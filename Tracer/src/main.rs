@@ -1,23 +1,330 @@
+use std::env;
+use std::process;
 use std::f64::consts::FRAC_PI_3;
 use std::sync::Arc;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
 
 extern crate lux;
 use lux::geom::Vec3;
 use lux::geom::Color;
 use lux::shape::*;
 use lux::scene::Camera;
+use lux::scene::PathTracer;
+use lux::scene::{CancelToken, Progress, RenderObserver};
+use lux::scene::TileOrder;
 use lux::scene::World;
+use lux::scene::loader;
+use lux::img::{ImageFormat, SaveOptions, ToneMap, TransferCurve};
+
+const USAGE: &'static str = "\
+Usage: lux [options] [scene.toml]
+
+Renders a scene file, or the built-in test scene if no file is given.
+
+Options:
+    -o, --output FILE        write the image to FILE, in a format picked by its
+                             extension: .png, .pfm, .hdr, .exr or .ppm
+                             (default: image.ppm)
+    -r, --resolution WxH     override the resolution given by the scene
+    -s, --samples N          trace N rays through each pixel
+    -d, --depth N            allow rays to bounce at most N times
+    -i, --integrator NAME    trace rays with whitted (the default) or path, an
+                             unbiased path tracer
+    -t, --threads N          render on N threads (default: one per core)
+        --tile-size N        trace the image in N by N pixel tiles (default: 32)
+        --tile-order ORDER   trace tiles in scanline, spiral (the default) or
+                             hilbert order
+        --seed N             seed the random numbers, for reproducible images
+        --time-limit SECS    stop tracing after SECS seconds and save the part
+                             of the image which is done
+    -e, --exposure STOPS     brighten (or darken, if negative) the image
+        --tone-map CURVE     squeeze bright colors with clamp (the default),
+                             reinhard, filmic or aces
+        --white-point X      the brightness that comes out as white (default:
+                             1 for clamp, none for reinhard and aces, 11.2
+                             for filmic)
+        --gamma G            encode with a power curve instead of sRGB
+                             (1 stores linear values)
+        --16-bit             write 16 bits per channel to PNG files
+    -h, --help               print this message";
+
+/// The settings given to lux on the command line.
+struct Options {
+    scene: Option<String>,
+    output: String,
+    resolution: Option<(i32, i32)>,
+    samples: Option<i32>,
+    depth: Option<i32>,
+    integrator: Option<String>,
+    threads: Option<usize>,
+    tile_size: Option<i32>,
+    tile_order: Option<TileOrder>,
+    seed: Option<u64>,
+    time_limit: Option<f64>,
+    save_options: SaveOptions
+}
 
 fn main() {
-    test_world();
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let options = match parse_args(&args) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        },
+        Err(message) => {
+            eprintln!("lux: {}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+
+    // Check the output format before spending any time rendering.
+    let format = match ImageFormat::from_filename(&options.output) {
+        Ok(format) => format,
+        Err(err) => {
+            eprintln!("lux: {}: {}", options.output, err);
+            process::exit(2);
+        }
+    };
+
+    let (mut world, mut camera) = match options.scene {
+        Some(ref filename) => match loader::load_scene(filename) {
+            Ok(scene) => scene,
+            Err(err) => {
+                eprintln!("lux: {}: {}", filename, err);
+                process::exit(1);
+            }
+        },
+        None => test_world()
+    };
+
+    if let Some((width, height)) = options.resolution {
+        camera.set_resolution(width, height);
+    }
+    if let Some(samples) = options.samples {
+        camera.set_samples(samples);
+    }
+    if let Some(depth) = options.depth {
+        world.set_max_depth(depth);
+    }
+    if options.integrator.as_ref().map_or(false, |name| name == "path") {
+        let mut path_tracer = PathTracer::new();
+        if let Some(depth) = options.depth {
+            path_tracer.set_max_depth(depth);
+        }
+        camera.set_integrator(Arc::new(path_tracer));
+    }
+    if let Some(threads) = options.threads {
+        camera.set_threads(threads);
+    }
+    if let Some(size) = options.tile_size {
+        camera.set_tile_size(size);
+    }
+    if let Some(order) = options.tile_order {
+        camera.set_tile_order(order);
+    }
+    if let Some(seed) = options.seed {
+        camera.set_seed(seed);
+    }
+
+    // The timer cancels the render once the time limit is up, unless it is told (by dropping
+    // done) that the render has already finished.
+    let cancel = CancelToken::new();
+    let (done, finished) = mpsc::channel::<()>();
+    let timer = options.time_limit.map(|limit| {
+        let cancel = cancel.clone();
+        thread::spawn(move || {
+            let limit = Duration::new(limit as u64, (limit.fract() * 1e9) as u32);
+            if finished.recv_timeout(limit) == Err(RecvTimeoutError::Timeout) {
+                cancel.cancel();
+            }
+        })
+    });
+
+    let mut progress = ProgressPrinter {complete: false};
+    let image = camera.render(&world, &mut progress, &cancel);
+    drop(done);
+    if let Some(timer) = timer {
+        timer.join().unwrap();
+    }
+
+    // The time limit may have been reached just after the last tile was traced, which leaves
+    // nothing missing.
+    if !progress.complete {
+        // Finish the progress line first.
+        eprintln!("\nlux: time limit reached, saving the partial image");
+    }
+
+    if let Err(err) = image.save_as(&options.output, format, &options.save_options) {
+        eprintln!("lux: {}: {}", options.output, err);
+        process::exit(1);
+    }
+}
+
+/// Prints the progress of a render to stderr, rewriting a single line.
+struct ProgressPrinter {
+    /// Whether every tile of the image has been traced.
+    complete: bool
+}
+
+impl RenderObserver for ProgressPrinter {
+    fn progress(&mut self, progress: &Progress) {
+        let seconds = progress.remaining.as_secs();
+        eprint!("\r{:5.1}% done, {}:{:02} left ", progress.percent(), seconds / 60, seconds % 60);
+
+        if progress.tiles_done == progress.tiles_total {
+            self.complete = true;
+            eprintln!();
+        }
+    }
+}
+
+/// Parses the command line arguments (without the program name). Returns Ok(None) if the usage
+/// message was asked for, and an error message if the arguments are invalid.
+fn parse_args(args: &[String]) -> Result<Option<Options>, String> {
+    let mut options = Options {scene: None, output: "image.ppm".to_string(), resolution: None,
+                               samples: None, depth: None, integrator: None,
+                               threads: None, tile_size: None, tile_order: None,
+                               seed: None, time_limit: None,
+                               save_options: SaveOptions::default()};
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        // Every option except --help and --16-bit takes a value.
+        let mut value = |name: &str| -> Result<String, String> {
+            iter.next().cloned().ok_or(format!("{} needs a value", name))
+        };
+
+        match &arg[..] {
+            "-h" | "--help" => return Ok(None),
+            "-o" | "--output" => options.output = value(arg)?,
+            "-r" | "--resolution" => options.resolution = Some(parse_resolution(&value(arg)?)?),
+            "-s" | "--samples" => options.samples = Some(parse_positive(arg, &value(arg)?)?),
+            "-d" | "--depth" => options.depth = Some(parse_non_negative(arg, &value(arg)?)?),
+            "-i" | "--integrator" => {
+                let name = value(arg)?;
+                if name != "whitted" && name != "path" {
+                    return Err(format!("unknown integrator \"{}\"", name));
+                }
+                options.integrator = Some(name);
+            },
+            "-t" | "--threads" => options.threads = Some(parse_positive(arg, &value(arg)?)?),
+            "--tile-size" => options.tile_size = Some(parse_positive(arg, &value(arg)?)?),
+            "--tile-order" => options.tile_order = Some(parse_tile_order(&value(arg)?)?),
+            "--seed" => options.seed = Some(parse_number(arg, &value(arg)?)?),
+            "--time-limit" => options.time_limit = Some(parse_positive_real(arg, &value(arg)?)?),
+            "-e" | "--exposure" => {
+                options.save_options.exposure = parse_number(arg, &value(arg)?)?;
+            },
+            "--tone-map" => options.save_options.tone_map = parse_tone_map(&value(arg)?)?,
+            "--white-point" => {
+                options.save_options.white_point = Some(parse_positive_real(arg, &value(arg)?)?);
+            },
+            "--gamma" => {
+                let gamma = parse_positive_real(arg, &value(arg)?)?;
+                options.save_options.transfer = if gamma == 1.0 {
+                    TransferCurve::Linear
+                } else {
+                    TransferCurve::Gamma(gamma)
+                };
+            },
+            "--16-bit" => options.save_options.sixteen_bit = true,
+            _ if arg.starts_with('-') && arg.len() > 1 => {
+                return Err(format!("unknown option {}", arg));
+            },
+            _ => {
+                if options.scene.is_some() {
+                    return Err("only one scene file can be rendered at a time".to_string());
+                }
+                options.scene = Some(arg.clone());
+            }
+        }
+    }
+
+    Ok(Some(options))
+}
+
+/// Parses a number of any type, naming the option in the error message.
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid value for {}: \"{}\"", name, value))
+}
+
+/// Parses a number which must be at least 1.
+fn parse_positive<T: std::str::FromStr + PartialOrd + From<u8>>(name: &str, value: &str)
+                                                                -> Result<T, String> {
+    let number: T = parse_number(name, value)?;
+
+    if number < T::from(1) {
+        return Err(format!("{} must be at least 1", name));
+    }
+
+    Ok(number)
+}
+
+/// Parses an integer which must be at least 0.
+fn parse_non_negative(name: &str, value: &str) -> Result<i32, String> {
+    let number: i32 = parse_number(name, value)?;
+
+    if number < 0 {
+        return Err(format!("{} can't be negative", name));
+    }
+
+    Ok(number)
+}
+
+/// Parses a real number which must be greater than 0.
+fn parse_positive_real(name: &str, value: &str) -> Result<f64, String> {
+    let number: f64 = parse_number(name, value)?;
+
+    if !(number > 0.0) || number.is_infinite() {
+        return Err(format!("{} must be greater than 0", name));
+    }
+
+    Ok(number)
+}
+
+/// Parses the name of a tone mapping curve.
+fn parse_tone_map(value: &str) -> Result<ToneMap, String> {
+    match value {
+        "clamp" => Ok(ToneMap::Clamp),
+        "reinhard" => Ok(ToneMap::Reinhard),
+        "filmic" => Ok(ToneMap::Filmic),
+        "aces" => Ok(ToneMap::Aces),
+        _ => Err(format!("unknown tone mapping curve \"{}\"", value))
+    }
+}
+
+/// Parses the name of a tile order.
+fn parse_tile_order(value: &str) -> Result<TileOrder, String> {
+    match value {
+        "scanline" => Ok(TileOrder::Scanline),
+        "spiral" => Ok(TileOrder::Spiral),
+        "hilbert" => Ok(TileOrder::Hilbert),
+        _ => Err(format!("unknown tile order \"{}\"", value))
+    }
+}
+
+/// Parses a resolution written as WIDTHxHEIGHT.
+fn parse_resolution(value: &str) -> Result<(i32, i32), String> {
+    let mut parts = value.split('x');
+
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(width), Some(height), None) => {
+            Ok((parse_positive("--resolution", width)?, parse_positive("--resolution", height)?))
+        },
+        _ => Err(format!("invalid resolution \"{}\", expected WIDTHxHEIGHT", value))
+    }
 }
 
-fn test_world() {
+fn test_world() -> (World, Camera) {
     let mut world = World::new(Color::new(0.9, 0.9, 0.9));
 
-    let plane_material = Arc::new(Material::lambertian(Color::new(1.0, 1.0, 1.0)));
+    let plane_material = Arc::new(Material::lambertian(Color::new(0.5, 0.5, 0.5)));
     let plane = Plane::new(Vec3::new(0.0, 1.0, 0.0), 0.0, plane_material);
-    let sphere1_material = Arc::new(Material::lambertian(Color::new(1.0, 0.0, 0.0)));
+    let sphere1_material = Arc::new(Material::lambertian(Color::new(0.5, 0.0, 0.0)));
     let sphere2_material = Arc::new(Material::glossy(Color::new(0.7, 1.0, 0.7), 1000.0));
     let sphere3_material = Arc::new(Material::glossy(Color::new(0.3, 0.3, 1.0), 10.0));
     let sphere1 = Sphere::new(Vec3::new(-1.2, 0.5, 0.0), 0.5, sphere1_material);
@@ -34,10 +341,10 @@ fn test_world() {
     world.add_shape(Arc::new(sphere1));
     world.add_shape(Arc::new(sphere2));
     world.add_shape(Arc::new(sphere3));
+    world.build_bvh();
 
     //world.add_light(Arc::new(light1));
     //world.add_light(Arc::new(light2));
 
-    let image = camera.trace_image(&world);
-    image.save("image.ppm");
+    (world, camera)
 }
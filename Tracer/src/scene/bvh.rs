@@ -0,0 +1,256 @@
+use std::f64::INFINITY;
+use geom::Ray;
+use geom::Vec3;
+use geom::AABB;
+use shape::Intersection;
+
+/// The number of buckets centroids are sorted into when searching for the best split.
+const BINS: usize = 16;
+
+/// Nodes with at most this many items are allowed to become leaves.
+const MAX_LEAF_SIZE: usize = 4;
+
+/// The cost of visiting a node, relative to the cost of intersecting a single item.
+const TRAVERSAL_COST: f64 = 1.0;
+
+/// BVH is a bounding volume hierarchy: a binary tree of bounding boxes used to quickly find which
+/// of a large number of items a Ray may hit.
+///
+/// The BVH only stores indices into the list of boxes it was built from. Callers keep the items
+/// themselves and hand the BVH a closure which intersects a single item by index. This way the
+/// same structure serves both the World (over its shapes) and TriangleMesh (over its Triangles).
+///
+/// It is built top-down, splitting each node where the surface area heuristic (SAH) estimates
+/// traversal is cheapest. To keep building fast, candidate splits are only evaluated between a
+/// fixed number of bins rather than between every item.
+pub struct BVH {
+    nodes: Vec<Node>,
+    indices: Vec<usize>
+}
+
+struct Node {
+    bounds: AABB,
+    kind: NodeKind
+}
+
+enum NodeKind {
+    /// A leaf refers to a run of `count` entries of BVH::indices, starting at `start`.
+    Leaf { start: usize, count: usize },
+    /// An interior node refers to its two children in BVH::nodes.
+    Interior { left: usize, right: usize }
+}
+
+impl BVH {
+    /// Builds a BVH over a list of bounding boxes. The index of each box in the slice is the index
+    /// later passed to the intersection closures.
+    pub fn new(bounds: &[AABB]) -> BVH {
+        let mut bvh = BVH {nodes: Vec::new(), indices: (0..bounds.len()).collect()};
+
+        if !bounds.is_empty() {
+            let centroids: Vec<Vec3> = bounds.iter().map(|b| b.centroid()).collect();
+            bvh.build(bounds, &centroids, 0, bounds.len());
+        }
+
+        bvh
+    }
+
+    /// Returns the number of items in the BVH.
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// Returns the bounding box of everything in the BVH, or None if it is empty.
+    pub fn bounds(&self) -> Option<AABB> {
+        self.nodes.first().map(|node| node.bounds)
+    }
+
+    /// Returns the closest intersection of a Ray with any item, where intersect(i) intersects the
+    /// Ray with the i-th item.
+    pub fn intersect_first<F>(&self, ray: &Ray, mut intersect: F) -> Option<Intersection>
+            where F: FnMut(usize) -> Option<Intersection> {
+        let mut distance = INFINITY;
+        let mut closest: Option<Intersection> = None;
+
+        self.traverse(ray, |index, _| {
+            if let Some(intersection) = intersect(index) {
+                if intersection.distance < distance {
+                    distance = intersection.distance;
+                    closest = Some(intersection);
+                }
+            }
+
+            Some(distance)
+        });
+
+        closest
+    }
+
+    /// Returns whether the Ray hits any item closer than max_distance. The search stops at the
+    /// first hit found, which makes this cheaper than intersect_first for shadow rays.
+    pub fn intersect_any<F>(&self, ray: &Ray, max_distance: f64, mut intersect: F) -> bool
+            where F: FnMut(usize) -> Option<Intersection> {
+        let mut hit = false;
+
+        self.traverse(ray, |index, limit| {
+            if let Some(intersection) = intersect(index) {
+                if intersection.distance < max_distance {
+                    hit = true;
+                    return None;
+                }
+            }
+
+            Some(limit)
+        });
+
+        hit
+    }
+
+    /// Walks the tree front to back, calling visit(index, limit) on every item whose bounding
+    /// boxes the Ray passes through closer than the current limit. visit returns the new limit,
+    /// so subtrees further away than the closest hit so far are skipped, or None to stop early.
+    fn traverse<F>(&self, ray: &Ray, mut visit: F) where F: FnMut(usize, f64) -> Option<f64> {
+        let root_entry = match self.nodes.first() {
+            Some(root) => root.bounds.intersect(ray, INFINITY),
+            None => None
+        };
+
+        let mut limit = INFINITY;
+        let mut stack = match root_entry {
+            Some(entry) => vec![(0, entry)],
+            None => return
+        };
+
+        while let Some((node_index, entry)) = stack.pop() {
+            if entry > limit {
+                continue;
+            }
+
+            match self.nodes[node_index].kind {
+                NodeKind::Leaf {start, count} => {
+                    for &index in self.indices[start..start + count].iter() {
+                        match visit(index, limit) {
+                            Some(new_limit) => limit = new_limit,
+                            None => return
+                        }
+                    }
+                },
+                NodeKind::Interior {left, right} => {
+                    let left_entry = self.nodes[left].bounds.intersect(ray, limit);
+                    let right_entry = self.nodes[right].bounds.intersect(ray, limit);
+
+                    // Push the further child first so the nearer one is visited first.
+                    match (left_entry, right_entry) {
+                        (Some(l), Some(r)) => {
+                            if l < r {
+                                stack.push((right, r));
+                                stack.push((left, l));
+                            } else {
+                                stack.push((left, l));
+                                stack.push((right, r));
+                            }
+                        },
+                        (Some(l), None) => stack.push((left, l)),
+                        (None, Some(r)) => stack.push((right, r)),
+                        (None, None) => {}
+                    }
+                }
+            }
+        }
+    }
+
+    /// Recursively builds the subtree over indices[start..end] and returns the index of its root.
+    fn build(&mut self, bounds: &[AABB], centroids: &[Vec3], start: usize, end: usize) -> usize {
+        let count = end - start;
+        let mut node_bounds = AABB::empty();
+        let mut centroid_bounds = AABB::empty();
+
+        for &index in self.indices[start..end].iter() {
+            node_bounds = node_bounds.union(&bounds[index]);
+            centroid_bounds = centroid_bounds.grow(centroids[index]);
+        }
+
+        let node_index = self.nodes.len();
+        self.nodes.push(Node {bounds: node_bounds,
+                              kind: NodeKind::Leaf {start: start, count: count}});
+
+        let axis = centroid_bounds.longest_axis();
+        let low = centroid_bounds.min[axis];
+        let extent = centroid_bounds.max[axis] - low;
+
+        // If every centroid is in the same spot, there's no way to split the items.
+        if count <= 1 || extent <= 0.0 {
+            return node_index;
+        }
+
+        let bin_of = |centroid: Vec3| -> usize {
+            (((centroid[axis] - low) / extent * (BINS as f64)) as usize).min(BINS - 1)
+        };
+
+        let mut bin_counts = [0; BINS];
+        let mut bin_bounds = [AABB::empty(); BINS];
+
+        for &index in self.indices[start..end].iter() {
+            let bin = bin_of(centroids[index]);
+            bin_counts[bin] += 1;
+            bin_bounds[bin] = bin_bounds[bin].union(&bounds[index]);
+        }
+
+        // Sweep from the right to get the area and count of everything right of each split...
+        let mut right_areas = [0.0; BINS];
+        let mut right_counts = [0; BINS];
+        let mut accumulated = AABB::empty();
+        let mut accumulated_count = 0;
+
+        for bin in (1..BINS).rev() {
+            accumulated = accumulated.union(&bin_bounds[bin]);
+            accumulated_count += bin_counts[bin];
+            right_areas[bin] = accumulated.surface_area();
+            right_counts[bin] = accumulated_count;
+        }
+
+        // ...then sweep from the left, pricing the split after each bin.
+        let mut best_cost = INFINITY;
+        let mut best_split = 0;
+        accumulated = AABB::empty();
+        accumulated_count = 0;
+
+        for bin in 0..BINS - 1 {
+            accumulated = accumulated.union(&bin_bounds[bin]);
+            accumulated_count += bin_counts[bin];
+
+            if accumulated_count == 0 || right_counts[bin + 1] == 0 {
+                continue;
+            }
+
+            let cost = accumulated.surface_area() * (accumulated_count as f64)
+                     + right_areas[bin + 1] * (right_counts[bin + 1] as f64);
+
+            if cost < best_cost {
+                best_cost = cost;
+                best_split = bin;
+            }
+        }
+
+        let area = node_bounds.surface_area();
+        let split_cost = if area > 0.0 { TRAVERSAL_COST + best_cost / area } else { INFINITY };
+
+        if count <= MAX_LEAF_SIZE && split_cost >= count as f64 {
+            return node_index;
+        }
+
+        // Partition the indices so everything left of the split comes first.
+        let mut mid = start;
+        for i in start..end {
+            if bin_of(centroids[self.indices[i]]) <= best_split {
+                self.indices.swap(i, mid);
+                mid += 1;
+            }
+        }
+
+        let left = self.build(bounds, centroids, start, mid);
+        let right = self.build(bounds, centroids, mid, end);
+        self.nodes[node_index].kind = NodeKind::Interior {left: left, right: right};
+
+        node_index
+    }
+}
@@ -1,30 +1,57 @@
-use ::rand::{Rng, thread_rng};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use std::thread;
+use ::Arc;
 use ::scope;
 use geom::Vec3;
 use geom::Ray;
 use geom::Color;
+use geom::random;
+use geom::random::jitter;
 use scene::World;
+use scene::integrator::{Integrator, Whitted};
+use scene::projection::Projection;
+use scene::observer::{CancelToken, Progress, RenderObserver};
+use scene::tiles;
+use scene::tiles::{Tile, TileOrder};
 use img::Image;
 
+/// The default number of rays traced through each pixel.
 const SAMPLES: i32 = 3;
 
+/// The default width and height of the tiles the image is split into.
+const TILE_SIZE: i32 = 32;
+
 /// Camera is the central point in the scene from which the rays are emitted.
 ///
-/// It defines the "film" where the rays are emitted by using a field of vision measurement which
-///  calculates an effective focal distance.
+/// It defines the "film" where the rays are emitted by using a projection, which is a perspective
+/// projection with a given field of vision unless the camera is told otherwise.
 pub struct Camera {
     location: Vec3,
     x: Vec3, //forward
     y: Vec3, //up
     z: Vec3, //right
-    distance: f64,
+    projection: Projection,
+    aperture: f64,
+    focus_distance: f64,
+    blades: u32,
     width: i32,
-    height: i32
+    height: i32,
+    samples: i32,
+    integrator: Arc<Integrator>,
+    threads: usize,
+    tile_size: i32,
+    tile_order: TileOrder,
+    seed: Option<u64>
 }
 
 impl Camera {
     /// Constructs a Camera struct using a location, direction, up vector, and field of vision
     /// measurement in radians.
+    ///
+    /// The field of vision is measured vertically; the horizontal field of vision follows from
+    /// the aspect ratio of the image.
     pub fn new(location: Vec3, lookat: Vec3, up: Vec3, fov: f64, width: i32, height: i32)
                -> Camera {
         let direction = (lookat - location).norm();
@@ -32,76 +59,258 @@ impl Camera {
                 x: direction,
                 z: (direction % up).norm(),
                 y: ((direction % up).norm() % direction).norm(),
-                distance: 0.5 / (fov / 2.0).tan(),
+                projection: Projection::Perspective {fov: fov},
+                aperture: 0.0,
+                focus_distance: (lookat - location).mag(),
+                blades: 0,
                 width: width,
-                height: height}
+                height: height,
+                samples: SAMPLES,
+                integrator: Arc::new(Whitted),
+                threads: default_threads(),
+                tile_size: TILE_SIZE,
+                tile_order: TileOrder::Spiral,
+                seed: None}
+    }
+
+    /// Replaces the camera's projection, which maps pixels onto rays.
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.projection = projection;
+    }
+
+    /// Sets the radius of the camera's lens. Anything closer or further than the focus distance
+    /// is blurred, more so with a bigger lens. A radius of 0 (the default) makes a pinhole
+    /// camera which keeps everything in focus.
+    pub fn set_aperture(&mut self, radius: f64) {
+        assert!(radius >= 0.0, "Cannot have a lens with a negative radius!");
+        self.aperture = radius;
+    }
+
+    /// Sets how far in front of the camera things are in perfect focus. Defaults to the
+    /// distance to the point the camera looks at.
+    pub fn set_focus_distance(&mut self, distance: f64) {
+        assert!(distance > 0.0, "Cannot focus on something behind the camera!");
+        self.focus_distance = distance;
+    }
+
+    /// Sets the number of blades in the lens' aperture, which gives out of focus highlights
+    /// (bokeh) the shape of a polygon. 0 (the default) makes a perfectly round aperture.
+    pub fn set_blades(&mut self, blades: u32) {
+        assert!(blades == 0 || blades >= 3, "Cannot make an aperture with fewer than 3 blades!");
+        self.blades = blades;
+    }
+
+    /// Changes the size of the traced image, keeping the vertical field of vision.
+    pub fn set_resolution(&mut self, width: i32, height: i32) {
+        assert!(width > 0 && height > 0, "Cannot trace an image with no pixels!");
+        self.width = width;
+        self.height = height;
+    }
+
+    /// Sets the number of rays traced through each pixel and averaged.
+    pub fn set_samples(&mut self, samples: i32) {
+        assert!(samples > 0, "Cannot trace an image with no samples per pixel!");
+        self.samples = samples;
+    }
+
+    /// Sets the integrator which finds the color seen along each ray. Defaults to Whitted.
+    pub fn set_integrator(&mut self, integrator: Arc<Integrator>) {
+        self.integrator = integrator;
+    }
+
+    /// Sets the number of threads the image is traced on. Defaults to the number of cores.
+    pub fn set_threads(&mut self, threads: usize) {
+        assert!(threads > 0, "Cannot trace an image on no threads!");
+        self.threads = threads;
+    }
+
+    /// Sets the size (in pixels) of the square tiles the image is split into for tracing.
+    pub fn set_tile_size(&mut self, size: i32) {
+        assert!(size > 0, "Cannot trace an image in empty tiles!");
+        self.tile_size = size;
+    }
+
+    /// Sets the order in which the tiles of the image are traced.
+    pub fn set_tile_order(&mut self, order: TileOrder) {
+        self.tile_order = order;
+    }
+
+    /// Sets the seed of the random numbers used while tracing. With a seed, tracing the same
+    /// scene twice gives exactly the same image, no matter how many threads are used.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
     }
 
     /// Traces an image and saves each pixel onto an image which is returned to the caller.
-    pub fn trace_image(&self, world: &World) -> Image {
+    ///
+    /// If cancel is cancelled, tracing stops early and the partially traced image is returned
+    /// (see render). Renders which can't be cancelled can pass CancelToken::new().
+    pub fn trace_image(&self, world: &World, cancel: &CancelToken) -> Image {
+        self.render(world, &mut (), cancel)
+    }
+
+    /// Traces an image like trace_image, telling observer about every finished tile and the
+    /// progress of the image.
+    ///
+    /// The image is split into tiles, which a pool of worker threads takes one at a time (in
+    /// the camera's tile order) until every tile is traced. Finished tiles are sent back to the
+    /// calling thread, which copies them into the image and notifies the observer.
+    ///
+    /// If cancel is cancelled, the workers stop after (or partway through) their current tiles
+    /// and the partially traced image is returned, with black where tiles weren't finished.
+    pub fn render(&self, world: &World, observer: &mut RenderObserver, cancel: &CancelToken)
+                  -> Image {
         let mut image = Image::new(self.width, self.height);
+        let tiles = tiles::split_image(self.width, self.height, self.tile_size, self.tile_order);
+        let next_tile = AtomicUsize::new(0);
+        let (sender, receiver) = mpsc::channel();
+        let start = Instant::now();
 
         scope(|scope| {
-            let mut futures = Vec::new();
-            futures.reserve((self.width * self.height) as usize);
-
-            for x in 0..self.width {
-                for y in 0..self.height {
-                    let thread = scope.spawn(move || {
-                        let mut color = Color::black();
-
-                        for _ in 0..SAMPLES {
-                            // get the ray that intersects a specific pixel
-                            let ray = self.get_ray_for_pixel((x as f64) + jitter(), (y as f64) + jitter());
-                            // trace the ray to get the color visible through the pixel
-                            color = color + world.trace_ray(&ray, 0);
-                            // save the pixel's color on "film"
-                        }
+            for _ in 0..self.threads.min(tiles.len()) {
+                let sender = sender.clone();
+                let tiles = &tiles;
+                let next_tile = &next_tile;
 
-                        color
-                    });
+                scope.spawn(move || {
+                    while !cancel.is_cancelled() {
+                        let index = next_tile.fetch_add(1, Ordering::SeqCst);
+                        if index >= tiles.len() {
+                            break;
+                        }
 
-                    futures.push(thread);
-                }
-                println!("{}% instantiated!", (x as f64) /
-                        (self.width as f64) * 100.0);
+                        match self.trace_tile(world, &tiles[index], cancel) {
+                            Some(colors) => sender.send((index, colors)).unwrap(),
+                            None => break
+                        }
+                    }
+                });
             }
 
-            for x in (0..self.width).rev() {
-                for y in (0..self.height).rev() {
-                    let thread_option = futures.pop();
+            // Once the workers' senders are dropped too, receiving stops after the last tile.
+            drop(sender);
+
+            let total = (self.width * self.height) as usize;
+            let mut pixels_done = 0;
+            let mut tiles_done = 0;
 
-                    let color: Color = if let Some(thread) = thread_option {
-                        thread.join()
-                    } else {
-                        panic!("Ran out of threads in the vec. Mismatched sizes?");
-                    };
+            for (index, colors) in receiver.iter() {
+                let tile = tiles[index];
 
-                    image.set_color(x, y, color * (1.0 / (SAMPLES as f64)));
+                for (i, &color) in colors.iter().enumerate() {
+                    let i = i as i32;
+                    image.set_color(tile.x + i % tile.width, tile.y + i / tile.width, color);
                 }
-                println!("{}% done!", (x as f64) /
-                        (self.width as f64) * 100.0);
+
+                pixels_done += tile.pixels();
+                tiles_done += 1;
+
+                let fraction = pixels_done as f64 / total as f64;
+                let elapsed = start.elapsed();
+                let seconds = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 * 1e-9;
+                let remaining = seconds * (1.0 - fraction) / fraction;
+
+                observer.tile_completed(&tile, &colors);
+                observer.progress(&Progress {
+                    tiles_done: tiles_done,
+                    tiles_total: tiles.len(),
+                    fraction: fraction,
+                    elapsed: elapsed,
+                    remaining: Duration::new(remaining as u64, (remaining.fract() * 1e9) as u32)
+                });
             }
         });
 
         image
     }
 
-    /// Returns a ray which intersects the 2-dimensional pixel (x, y) on the view plane constructed
-    /// from our field of vision.
-    fn get_ray_for_pixel(&self, x: f64, y: f64) -> Ray {
+    /// Traces every pixel in a tile, returning their colors row by row, or None if the render
+    /// was cancelled before the tile was finished.
+    fn trace_tile(&self, world: &World, tile: &Tile, cancel: &CancelToken) -> Option<Vec<Color>> {
+        let mut colors = Vec::with_capacity(tile.pixels());
+
+        for y in tile.y..tile.y + tile.height {
+            if cancel.is_cancelled() {
+                return None;
+            }
+
+            for x in tile.x..tile.x + tile.width {
+                // Reseeding per pixel keeps seeded images independent of the number of threads,
+                // the tile size and the tile order.
+                if let Some(seed) = self.seed {
+                    random::seed(random::derive_seed(seed, (y * self.width + x) as u64));
+                }
+
+                colors.push(self.trace_pixel(world, x, y));
+            }
+        }
+
+        Some(colors)
+    }
+
+    /// Traces the samples through the pixel (x, y) and averages them.
+    fn trace_pixel(&self, world: &World, x: i32, y: i32) -> Color {
+        let mut color = Color::black();
+
+        for _ in 0..self.samples {
+            // get the ray that intersects a specific pixel, if the projection has one
+            let ray = self.get_ray_for_pixel((x as f64) + jitter(), (y as f64) + jitter());
+            // trace the ray to get the color visible through the pixel
+            if let Some(ray) = ray {
+                color = color + self.integrator.radiance(world, &ray);
+            }
+        }
+
+        color * (1.0 / (self.samples as f64))
+    }
+
+    /// Returns a ray which intersects the 2-dimensional pixel (x, y) on the film, as given by
+    /// the camera's projection. Returns None if the projection doesn't cover the pixel.
+    ///
+    /// With an aperture, the ray starts from a random point on the lens and goes through the
+    /// point on the focal plane (or sphere) which the pixel is focused on.
+    fn get_ray_for_pixel(&self, x: f64, y: f64) -> Option<Ray> {
         assert!(x >= -1.0 && x < (self.width as f64) + 1.0, "Pixel x-coordinate out of bounds!");
         assert!(y >= -1.0 && y < (self.height as f64) + 1.0, "Pixel y-coordinate out of bounds!");
 
-        let pixel_dir = self.x * self.distance
-                     + self.z * (x / (self.width as f64) - 0.5)
-                     + self.y * (0.5 - y / (self.height as f64));
+        let aspect = (self.width as f64) / (self.height as f64);
+        let u = (x / (self.width as f64) - 0.5) * aspect;
+        let v = 0.5 - y / (self.height as f64);
+
+        let (origin, direction) = match self.projection.local_ray(u, v, aspect) {
+            Some(ray) => ray,
+            None => return None
+        };
+
+        // The projection works in (forward, up, right) coordinates.
+        let to_world = |a: Vec3| self.x * a.x + self.y * a.y + self.z * a.z;
+        let origin = self.location + to_world(origin);
+        let direction = to_world(direction);
+
+        if self.aperture == 0.0 {
+            return Some(Ray::new(origin, direction));
+        }
+
+        // The forward component of the normalized direction is the cosine of its angle to the
+        // camera's axis, so dividing by it lands on the focal plane.
+        let focus = if self.projection.has_focal_plane() {
+            origin + direction * (self.focus_distance / (direction * self.x))
+        } else {
+            origin + direction * self.focus_distance
+        };
+
+        let (u, v) = if self.blades == 0 {
+            random::disk()
+        } else {
+            random::polygon(self.blades, 0.0)
+        };
+        let lens = origin + self.z * (u * self.aperture) + self.y * (v * self.aperture);
 
-        Ray::new(self.location, pixel_dir)
+        Some(Ray::new(lens, focus - lens))
     }
 }
 
-/// A helper method which returns a random real on [0, 1).
-fn jitter() -> f64 {
-    thread_rng().next_f64()
+/// Returns the number of cores the machine has, or 1 if it can't be found.
+fn default_threads() -> usize {
+    thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
 }
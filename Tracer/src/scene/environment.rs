@@ -0,0 +1,187 @@
+use std::f64::consts::PI;
+
+use geom::Vec3;
+use geom::Color;
+use geom::random::jitter;
+use img::Image;
+use scene::Sky;
+
+/// Environment is the light arriving from infinitely far away, seen by every ray which doesn't
+/// hit anything.
+pub enum Environment {
+    /// The same color in every direction.
+    Constant(Color),
+    /// A simple sky, which blends from the horizon color straight up to the zenith color and
+    /// straight down to the ground color.
+    Gradient { zenith: Color, horizon: Color, ground: Color },
+    /// An equirectangular image of the surroundings.
+    Map(EnvironmentMap),
+    /// A physically based daytime sky.
+    Sky(Sky)
+}
+
+impl Environment {
+    /// Returns the light arriving from (normalized) direction.
+    pub fn color(&self, direction: Vec3) -> Color {
+        match *self {
+            Environment::Constant(color) => color,
+            Environment::Gradient {zenith, horizon, ground} => {
+                if direction.y >= 0.0 {
+                    horizon * (1.0 - direction.y) + zenith * direction.y
+                } else {
+                    horizon * (1.0 + direction.y) + ground * -direction.y
+                }
+            },
+            Environment::Map(ref map) => map.color(direction),
+            Environment::Sky(ref sky) => sky.color(direction)
+        }
+    }
+
+    /// Picks a direction to look for light in, more likely towards brighter parts of the
+    /// environment, and returns it with its probability density over solid angle.
+    ///
+    /// Only maps are worth sampling this way, so this returns None for anything else (and for
+    /// maps which are completely black).
+    pub fn sample(&self) -> Option<(Vec3, f64)> {
+        match *self {
+            Environment::Map(ref map) => map.sample(),
+            _ => None
+        }
+    }
+
+    /// Returns the probability density of sample picking direction. This is 0 for environments
+    /// which can't be sampled.
+    pub fn pdf(&self, direction: Vec3) -> f64 {
+        match *self {
+            Environment::Map(ref map) => map.pdf(direction),
+            _ => 0.0
+        }
+    }
+}
+
+/// EnvironmentMap is an equirectangular (latitude-longitude) image wrapped around the scene,
+/// usually a high dynamic range photograph of a real place.
+///
+/// The center of the image is towards +x with +z to its right, and the top row is straight up,
+/// the way an equirectangular Camera at the origin looking along +x would see it. To sample the
+/// map by brightness, it keeps the cumulative distribution of its pixels' luminance.
+pub struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    /// The pixels, row by row from the top.
+    pixels: Vec<Color>,
+    /// The running sums of each pixel's weight along its row.
+    column_cdfs: Vec<Vec<f64>>,
+    /// The running sum of the rows' total weights.
+    row_cdf: Vec<f64>
+}
+
+impl EnvironmentMap {
+    /// Constructs a map from an image, with its colors scaled by intensity.
+    pub fn new(image: &Image, intensity: f64) -> EnvironmentMap {
+        let width = image.get_width() as usize;
+        let height = image.get_height() as usize;
+
+        let mut pixels = Vec::with_capacity(width * height);
+        let mut column_cdfs = Vec::with_capacity(height);
+        let mut row_cdf = Vec::with_capacity(height);
+        let mut total = 0.0;
+
+        for y in 0..height {
+            // Rows near the poles are squeezed onto a smaller part of the sphere.
+            let sin_theta = ((y as f64 + 0.5) / height as f64 * PI).sin();
+            let mut cdf = Vec::with_capacity(width);
+            let mut sum = 0.0;
+
+            for x in 0..width {
+                let color = image.get_color(x as i32, y as i32) * intensity;
+                sum += color.luminance().max(0.0) * sin_theta;
+                pixels.push(color);
+                cdf.push(sum);
+            }
+
+            total += sum;
+            column_cdfs.push(cdf);
+            row_cdf.push(total);
+        }
+
+        EnvironmentMap {width: width, height: height, pixels: pixels, column_cdfs: column_cdfs,
+                        row_cdf: row_cdf}
+    }
+
+    /// Returns the color of the pixel seen in (normalized) direction.
+    pub fn color(&self, direction: Vec3) -> Color {
+        let (x, y) = self.pixel(direction);
+        self.pixels[y * self.width + x]
+    }
+
+    /// Picks a direction with a probability proportional to how bright it is, returning it with
+    /// its probability density over solid angle.
+    pub fn sample(&self) -> Option<(Vec3, f64)> {
+        let total = self.row_cdf[self.height - 1];
+        if total <= 0.0 {
+            return None;
+        }
+
+        let y = find(&self.row_cdf, jitter() * total);
+        let row_total = self.column_cdfs[y][self.width - 1];
+        let x = find(&self.column_cdfs[y], jitter() * row_total);
+
+        let u = (x as f64 + jitter()) / self.width as f64;
+        let v = (y as f64 + jitter()) / self.height as f64;
+        let phi = (u - 0.5) * 2.0 * PI;
+        let theta = v * PI;
+        let direction = Vec3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin());
+
+        let pdf = self.pdf(direction);
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        Some((direction, pdf))
+    }
+
+    /// Returns the probability density of sample picking (normalized) direction.
+    pub fn pdf(&self, direction: Vec3) -> f64 {
+        let total = self.row_cdf[self.height - 1];
+        let sin_theta = (1.0 - direction.y * direction.y).max(0.0).sqrt();
+        if total <= 0.0 || sin_theta == 0.0 {
+            return 0.0;
+        }
+
+        let (x, y) = self.pixel(direction);
+        let cdf = &self.column_cdfs[y];
+        let weight = cdf[x] - if x == 0 { 0.0 } else { cdf[x - 1] };
+
+        // The density over the image, turned into one over the sphere. The image covers 2π
+        // by π radians, and a patch of it is squeezed by sin θ when it is wrapped around.
+        let image_pdf = weight / total * (self.width * self.height) as f64;
+        image_pdf / (2.0 * PI * PI * sin_theta)
+    }
+
+    /// Finds the pixel seen in (normalized) direction.
+    fn pixel(&self, direction: Vec3) -> (usize, usize) {
+        let u = 0.5 + direction.z.atan2(direction.x) / (2.0 * PI);
+        let v = direction.y.max(-1.0).min(1.0).acos() / PI;
+
+        let x = ((u * self.width as f64) as usize).min(self.width - 1);
+        let y = ((v * self.height as f64) as usize).min(self.height - 1);
+        (x, y)
+    }
+}
+
+/// Finds the first entry of a running sum which is greater than value.
+fn find(cdf: &[f64], value: f64) -> usize {
+    let (mut low, mut high) = (0, cdf.len() - 1);
+
+    while low < high {
+        let middle = (low + high) / 2;
+        if cdf[middle] > value {
+            high = middle;
+        } else {
+            low = middle + 1;
+        }
+    }
+
+    low
+}
@@ -0,0 +1,24 @@
+use geom::Color;
+use geom::Ray;
+use scene::World;
+
+/// An Integrator finds the light arriving along a ray by following it (and whatever rays it
+/// spawns) through a World. It is how the Camera turns rays into colors.
+///
+/// Every integrator treats lights, emission and BSDFs in the same physical units, so switching
+/// integrators changes the noise and the effects traced, but not how bright a scene is.
+pub trait Integrator : Send + Sync {
+    /// Returns the radiance arriving at the ray's origin from the direction it points in.
+    fn radiance(&self, world: &World, ray: &Ray) -> Color;
+}
+
+/// Whitted traces rays the way Lux always has, with World::trace_ray: direct light from each
+/// light, plus one recursive ray sampled from the material's BSDF, up to the World's maximum
+/// depth.
+pub struct Whitted;
+
+impl Integrator for Whitted {
+    fn radiance(&self, world: &World, ray: &Ray) -> Color {
+        world.trace_ray(ray, 0)
+    }
+}
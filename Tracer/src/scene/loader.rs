@@ -0,0 +1,1162 @@
+use ::Arc;
+use std::collections::HashMap;
+use std::f64::consts::FRAC_PI_3;
+use std::error;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+use geom::Vec3;
+use geom::Color;
+use geom::Transform;
+use shape::*;
+use img::Image;
+use scene::Camera;
+use scene::{FisheyeMapping, Projection};
+use scene::World;
+use scene::{Environment, EnvironmentMap, Sky};
+use scene::toml;
+use scene::toml::{Table, Value, ValueKind};
+
+/// LoadError is returned when a scene file can't be read or doesn't describe a valid scene.
+#[derive(Debug)]
+pub enum LoadError {
+    /// The scene file couldn't be read.
+    Io(io::Error),
+    /// The scene file is malformed or inconsistent. The line is where the problem was found.
+    Invalid { line: usize, message: String }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoadError::Io(ref err) => write!(f, "could not read scene: {}", err),
+            LoadError::Invalid {line, ref message} => write!(f, "line {}: {}", line, message)
+        }
+    }
+}
+
+impl error::Error for LoadError {
+    fn description(&self) -> &str {
+        match *self {
+            LoadError::Io(_) => "could not read scene",
+            LoadError::Invalid {..} => "invalid scene"
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(err: io::Error) -> LoadError {
+        LoadError::Io(err)
+    }
+}
+
+impl From<toml::TomlError> for LoadError {
+    fn from(err: toml::TomlError) -> LoadError {
+        LoadError::Invalid {line: err.line, message: err.message}
+    }
+}
+
+/// Loads the scene file at filename and builds the World and Camera it describes.
+///
+/// Scene files are written in (a subset of) TOML. They contain one [camera] table, an optional
+/// [world] table, and any number of [[material]], [[shape]] and [[light]] tables:
+///
+/// ```toml
+/// [camera]
+/// location = [-1.5, 1.0, 3.0]
+/// look_at = [-0.3, 0.5, 0.0]
+/// fov = 54            # degrees
+/// width = 400
+/// height = 400
+/// projection = "perspective"  # or orthographic (with view_height), fisheye (with fov and
+///                             # a mapping, equidistant or equisolid) or equirectangular
+/// aperture = 0.05     # optional lens radius, for depth of field
+/// focus_distance = 3  # optional, defaults to the distance to look_at
+/// blades = 6          # optional, gives the aperture a polygonal shape
+///
+/// [world]
+/// background = [0.9, 0.9, 0.9]
+/// # or environment = "gradient", with zenith, horizon and ground colors, or environment = "map"
+/// # with an equirectangular .hdr or .pfm file and an optional intensity, or environment = "sky"
+/// # with the direction of the sun and an optional turbidity (3 by default). A sky also adds a
+/// # light for the sun, unless sun_light = false.
+///
+/// [[material]]
+/// name = "red"
+/// type = "lambertian"
+/// color = [1.0, 0.0, 0.0]
+/// emission = [1.0, 0.9, 0.8] # optional glow, on any type of material
+/// strength = 4        # optional, scales the emission
+///
+/// [[material]]
+/// name = "gold"
+/// type = "microfacet" # physically based, with a base color
+/// color = [1.0, 0.78, 0.34]
+/// metallic = 1        # optional, from 0 (the default) to 1
+/// roughness = 0.3     # optional, from 0 to 1 (0.5 by default)
+///
+/// [[material]]
+/// name = "glass"
+/// type = "dielectric" # reflects and refracts by the Fresnel equations
+/// eta = 1.5           # the index of refraction
+/// absorption = [0.6, 0.9, 0.8] # optional color left after light travels absorption_distance
+/// absorption_distance = 1 # (1 by default) inside; allowed on any material
+///
+/// [[material]]
+/// name = "lamp"
+/// type = "emissive"   # only glows, with color scaled by an optional strength; shapes made of
+/// color = [1.0, 1.0, 1.0] # glowing materials also light the scene, unless they are unbounded
+///
+/// [[material]]
+/// name = "rusty"
+/// type = "mix"        # blends the surfaces of two materials defined above
+/// a = "gold"
+/// b = "red"
+/// weight = 0.3        # optional amount of b (0.5 by default), or texture = "checker" or
+///                     # "noise", with an optional scale (and octaves for noise)
+///
+/// [[material]]
+/// name = "lacquer"
+/// type = "layered"    # a clear coat over a material defined above
+/// base = "red"
+/// eta = 1.5           # optional, the coat's index of refraction
+/// color = [1.0, 1.0, 1.0] # optional tint of the coat
+///
+/// [[shape]]
+/// type = "sphere"
+/// material = "red"
+/// center = [-1.2, 0.5, 0.0]
+/// radius = 0.5
+///
+/// [[light]]
+/// type = "point"
+/// position = [10.0, 10.0, 3.0]
+/// color = [1.0, 1.0, 1.0]
+/// intensity = 150     # optional, scales the color
+/// attenuation = [0.0, 0.0, 1.0] # optional [constant, linear, quadratic] falloff with distance,
+///                     # which defaults to inverse square; point and spot lights only
+///
+/// [[light]]
+/// type = "rect"       # or disk (with normal and radius) or sphere (with radius)
+/// center = [0.0, 3.0, 0.0]
+/// u = [1.0, 0.0, 0.0]
+/// v = [0.0, 0.0, -1.0] # the light shines towards u x v
+/// samples = 16        # optional number of shadow rays per lit point
+///
+/// [[light]]
+/// type = "spot"
+/// position = [0.0, 3.0, 0.0]
+/// direction = [0.0, -1.0, 0.0]
+/// inner_angle = 20    # degrees; full strength inside, fading out to outer_angle
+/// outer_angle = 30
+/// ies = "downlight.ies" # optional measured profile, also allowed on point lights (with an
+///                     # axis, which defaults to straight down)
+/// ```
+///
+/// Paths in the file (like the file of a "mesh" shape) are relative to the scene file. Colors
+/// of lights and glowing materials are radiometric, and come out equally bright with either
+/// integrator: a white matte surface lit head on by a point light of intensity I at distance d
+/// looks I / (π d²) bright.
+pub fn load_scene(filename: &str) -> Result<(World, Camera), LoadError> {
+    let mut source = String::new();
+    File::open(filename)?.read_to_string(&mut source)?;
+
+    let base_dir = Path::new(filename).parent().unwrap_or(Path::new(""));
+    parse_scene(&source, base_dir)
+}
+
+/// Builds the World and Camera described by the text of a scene file. Relative paths in the
+/// scene are resolved against base_dir.
+pub fn parse_scene(source: &str, base_dir: &Path) -> Result<(World, Camera), LoadError> {
+    let tables = toml::parse(source)?;
+    let mut builder = SceneBuilder {base_dir: base_dir, materials: HashMap::new(),
+                                    shapes: Vec::new(), lights: Vec::new(),
+                                    camera: None, environment: None};
+
+    for table in tables.iter() {
+        match &table.name[..] {
+            // Keys before the first header.
+            "" => if let Some(&(ref key, ref value)) = table.entries.first() {
+                return invalid(value.line, format!("key \"{}\" is not in any table", key));
+            },
+            "camera" => builder.add_camera(table)?,
+            "world" => builder.add_world(table)?,
+            "material" => builder.add_material(table)?,
+            "shape" => builder.add_shape(table)?,
+            "light" => builder.add_light(table)?,
+            name => return invalid(table.line, format!("unknown table [{}]", name))
+        }
+    }
+
+    let camera = match builder.camera {
+        Some(camera) => camera,
+        None => return invalid(1, "the scene has no [camera]".to_string())
+    };
+
+    let mut world = World::new(Color::black());
+    if let Some(environment) = builder.environment {
+        world.set_environment(environment);
+    }
+
+    for entry in builder.shapes.into_iter() {
+        if entry.used {
+            continue;
+        }
+
+        if entry.shape.get_material().is_emissive() {
+            world.add_emitter(entry.shape);
+        } else {
+            world.add_shape(entry.shape);
+        }
+    }
+
+    for light in builder.lights.into_iter() {
+        world.add_light(light);
+    }
+
+    world.build_bvh();
+
+    Ok((world, camera))
+}
+
+/// Returns a LoadError::Invalid as the Err of any Result.
+fn invalid<T>(line: usize, message: String) -> Result<T, LoadError> {
+    Err(LoadError::Invalid {line: line, message: message})
+}
+
+/// A shape which has been built, along with its name (if it has one) and whether another shape
+/// (a CSG shape or an instance) is made out of it. Used shapes aren't added to the World
+/// themselves.
+struct ShapeEntry {
+    name: Option<String>,
+    shape: Arc<Shape>,
+    used: bool
+}
+
+/// SceneBuilder collects everything defined so far while the tables are read in order, so that
+/// tables can refer to materials and shapes defined above them.
+struct SceneBuilder<'a> {
+    base_dir: &'a Path,
+    materials: HashMap<String, Arc<Material>>,
+    shapes: Vec<ShapeEntry>,
+    lights: Vec<Arc<Light>>,
+    camera: Option<Camera>,
+    environment: Option<Environment>
+}
+
+impl<'a> SceneBuilder<'a> {
+    fn add_camera(&mut self, table: &Table) -> Result<(), LoadError> {
+        if self.camera.is_some() {
+            return invalid(table.line, "the scene has more than one [camera]".to_string());
+        }
+
+        let mut fields = Fields::new(table);
+        let location = fields.vec3("location")?;
+        let look_at = fields.vec3("look_at")?;
+        let up = fields.vec3_or("up", Vec3::up())?;
+        let width = fields.positive_integer("width")?;
+        let height = fields.positive_integer("height")?;
+
+        let (kind, kind_line) = fields.optional_string("projection")?
+                                      .unwrap_or(("perspective".to_string(), table.line));
+
+        let projection = match &kind[..] {
+            "perspective" => {
+                let fov = fields.number("fov")?;
+                if fov <= 0.0 || fov >= 180.0 {
+                    return invalid(fields.line("fov"), "a perspective camera's fov must be \
+                                                        above 0 and below 180".to_string());
+                }
+                Projection::Perspective {fov: fov.to_radians()}
+            },
+            "orthographic" => {
+                let height = fields.number("view_height")?;
+                if height <= 0.0 {
+                    return invalid(fields.line("view_height"), "the camera's view_height must \
+                                                                be positive".to_string());
+                }
+                Projection::Orthographic {height: height}
+            },
+            "fisheye" => {
+                let fov = fields.number("fov")?;
+                if fov <= 0.0 || fov > 360.0 {
+                    return invalid(fields.line("fov"), "a fisheye camera's fov must be above 0 \
+                                                        and at most 360".to_string());
+                }
+
+                let mapping = match fields.optional_string("mapping")? {
+                    None => FisheyeMapping::Equidistant,
+                    Some((ref mapping, _)) if mapping == "equidistant" => {
+                        FisheyeMapping::Equidistant
+                    },
+                    Some((ref mapping, _)) if mapping == "equisolid" => FisheyeMapping::Equisolid,
+                    Some((mapping, line)) => {
+                        return invalid(line, format!("unknown fisheye mapping \"{}\"", mapping));
+                    }
+                };
+                Projection::Fisheye {fov: fov.to_radians(), mapping: mapping}
+            },
+            "equirectangular" => Projection::Equirectangular,
+            _ => return invalid(kind_line, format!("unknown projection \"{}\"", kind))
+        };
+
+        let aperture = fields.number_or("aperture", 0.0)?;
+        let focus_distance = fields.optional_number("focus_distance")?;
+        let blades = fields.number_or("blades", 0.0)?;
+        fields.finish()?;
+
+        if aperture < 0.0 {
+            return invalid(fields.line("aperture"),
+                           "the camera's aperture can't be negative".to_string());
+        }
+        if blades != 0.0 && (blades < 3.0 || blades.fract() != 0.0) {
+            return invalid(fields.line("blades"), "the camera's blades must be 0 or a whole \
+                                                   number of at least 3".to_string());
+        }
+
+        // Camera::new always makes a perspective camera, whose fov is replaced right away.
+        let mut camera = Camera::new(location, look_at, up, FRAC_PI_3, width, height);
+        camera.set_projection(projection);
+        camera.set_aperture(aperture);
+        camera.set_blades(blades as u32);
+        if let Some(distance) = focus_distance {
+            if distance <= 0.0 {
+                return invalid(fields.line("focus_distance"),
+                               "the camera's focus_distance must be positive".to_string());
+            }
+            camera.set_focus_distance(distance);
+        }
+
+        self.camera = Some(camera);
+        Ok(())
+    }
+
+    fn add_world(&mut self, table: &Table) -> Result<(), LoadError> {
+        if self.environment.is_some() {
+            return invalid(table.line, "the scene has more than one [world]".to_string());
+        }
+
+        let mut fields = Fields::new(table);
+        let kind = fields.optional_string("environment")?;
+
+        let environment = match kind {
+            None => Environment::Constant(fields.color_or("background", Color::black())?),
+            Some((ref kind, _)) if kind == "gradient" => Environment::Gradient {
+                zenith: fields.color("zenith")?,
+                horizon: fields.color("horizon")?,
+                ground: fields.color("ground")?
+            },
+            Some((ref kind, _)) if kind == "map" => {
+                let (file, line) = fields.string("file")?;
+                let path = self.base_dir.join(&file);
+                let intensity = fields.number_or("intensity", 1.0)?;
+
+                match Image::load(&path.to_string_lossy()) {
+                    Ok(image) => Environment::Map(EnvironmentMap::new(&image, intensity)),
+                    Err(err) => return invalid(line, format!("could not load environment map \
+                                                             \"{}\": {}", file, err))
+                }
+            },
+            Some((ref kind, _)) if kind == "sky" => {
+                let sun = fields.direction("sun")?;
+                let turbidity = fields.number_or("turbidity", 3.0)?;
+                if turbidity < 1.0 {
+                    return invalid(fields.line("turbidity"),
+                                   "the sky's turbidity must be at least 1".to_string());
+                }
+
+                let sky = Sky::new(sun, turbidity);
+                if fields.boolean_or("sun_light", true)? {
+                    self.lights.push(Arc::new(sky.sun_light()));
+                }
+                Environment::Sky(sky)
+            },
+            Some((kind, line)) => {
+                return invalid(line, format!("unknown environment \"{}\" (expected gradient, \
+                                              map or sky)", kind))
+            }
+        };
+
+        self.environment = Some(environment);
+        fields.finish()
+    }
+
+    fn add_material(&mut self, table: &Table) -> Result<(), LoadError> {
+        let mut fields = Fields::new(table);
+        let (name, name_line) = fields.string("name")?;
+        let (kind, kind_line) = fields.string("type")?;
+
+        let mut material = match &kind[..] {
+            "lambertian" => Material::lambertian(fields.color("color")?),
+            "reflective" => Material::reflective(fields.color("color")?),
+            "glossy" => {
+                let power = fields.number("power")?;
+                if power < 0.0 {
+                    return invalid(fields.line("power"),
+                                   "a glossy material's power can't be negative".to_string());
+                }
+                Material::glossy(fields.color("color")?, power)
+            },
+            "transparent" => {
+                let color = fields.color("color")?;
+                let eta = fields.number("eta")?;
+                if eta <= 0.0 {
+                    return invalid(fields.line("eta"),
+                                   "the index of refraction must be positive".to_string());
+                }
+                Material::transparent(color, eta)
+            },
+            "custom" => {
+                let glossy_power = fields.number_or("glossy_power", 0.0)?;
+                let trans_eta = fields.number_or("trans_eta", 1.0)?;
+                if glossy_power < 0.0 && glossy_power != -1.0 {
+                    return invalid(fields.line("glossy_power"),
+                                   "glossy_power must be -1 (a mirror) or at least 0".to_string());
+                }
+                if trans_eta <= 0.0 {
+                    return invalid(fields.line("trans_eta"),
+                                   "the index of refraction must be positive".to_string());
+                }
+
+                Material::custom(fields.number_or("matte_intensity", 0.0)?,
+                                 fields.color_or("matte_color", Color::black())?,
+                                 fields.number_or("glossy_intensity", 0.0)?,
+                                 glossy_power,
+                                 fields.color_or("glossy_color", Color::black())?,
+                                 fields.number_or("trans_intensity", 0.0)?,
+                                 fields.color_or("trans_color", Color::black())?,
+                                 trans_eta)
+            },
+            "microfacet" => {
+                let color = fields.color("color")?;
+                let metallic = fields.number_or("metallic", 0.0)?;
+                let roughness = fields.number_or("roughness", 0.5)?;
+
+                if metallic < 0.0 || metallic > 1.0 {
+                    return invalid(fields.line("metallic"),
+                                   "metallic must be between 0 and 1".to_string());
+                }
+                if roughness < 0.0 || roughness > 1.0 {
+                    return invalid(fields.line("roughness"),
+                                   "roughness must be between 0 and 1".to_string());
+                }
+                Material::microfacet(color, metallic, roughness)
+            },
+            "dielectric" => {
+                let eta = fields.number("eta")?;
+                if eta <= 0.0 {
+                    return invalid(fields.line("eta"),
+                                   "the index of refraction must be positive".to_string());
+                }
+                Material::dielectric(fields.color_or("color", Color::white())?, eta)
+            },
+            "emissive" => Material::emissive(fields.color("color")?, fields.strength()?),
+            "mix" => {
+                let a = self.material(&mut fields, "a")?;
+                let b = self.material(&mut fields, "b")?;
+                Material::mix_texture(a, b, fields.weight()?)
+            },
+            "layered" => {
+                let base = self.material(&mut fields, "base")?;
+                let eta = fields.number_or("eta", 1.5)?;
+                if eta <= 0.0 {
+                    return invalid(fields.line("eta"),
+                                   "the index of refraction must be positive".to_string());
+                }
+                Material::layered(base, eta, fields.color_or("color", Color::white())?)
+            },
+            _ => return invalid(kind_line, format!("unknown material type \"{}\"", kind))
+        };
+
+        if kind != "emissive" && fields.has("emission") {
+            material.set_emission(fields.color("emission")?, fields.strength()?);
+        }
+
+        if fields.has("absorption") {
+            let color = fields.color("absorption")?;
+            let distance = fields.number_or("absorption_distance", 1.0)?;
+
+            if color.r <= 0.0 || color.g <= 0.0 || color.b <= 0.0 {
+                return invalid(fields.line("absorption"),
+                               "the absorption color must be above 0".to_string());
+            }
+            if distance <= 0.0 {
+                return invalid(fields.line("absorption_distance"),
+                               "absorption_distance must be positive".to_string());
+            }
+            material.set_absorption(color, distance);
+        }
+        fields.finish()?;
+
+        if self.materials.contains_key(&name) {
+            return invalid(name_line, format!("material \"{}\" is defined twice", name));
+        }
+
+        self.materials.insert(name, Arc::new(material));
+        Ok(())
+    }
+
+    fn add_shape(&mut self, table: &Table) -> Result<(), LoadError> {
+        let mut fields = Fields::new(table);
+        let (kind, kind_line) = fields.string("type")?;
+        let name = fields.optional_string("name")?;
+        let mut transform = self.transform(&mut fields)?;
+
+        let shape: Arc<Shape> = match &kind[..] {
+            "sphere" => Arc::new(Sphere::new(fields.vec3("center")?,
+                                             fields.positive_number("radius")?,
+                                             self.material(&mut fields, "material")?)),
+            "plane" => Arc::new(Plane::new(fields.direction("normal")?.norm(),
+                                           fields.number_or("distance", 0.0)?,
+                                           self.material(&mut fields, "material")?)),
+            "cylinder" => {
+                let start = fields.vec3("start")?;
+                let axis = fields.direction("axis")?;
+                let radius = fields.positive_number("radius")?;
+                let height = fields.positive_number("height")?;
+                let material = self.material(&mut fields, "material")?;
+
+                if fields.boolean_or("capped", true)? {
+                    Arc::new(Cylinder::new(start, axis, radius, height, material))
+                } else {
+                    Arc::new(Cylinder::open(start, axis, radius, height, material))
+                }
+            },
+            "triangle" => {
+                let (vertices, line) = fields.vec3_list("vertices")?;
+                if vertices.len() != 3 {
+                    return invalid(line, "a triangle needs exactly 3 vertices".to_string());
+                }
+
+                Arc::new(Triangle::new(vertices[0], vertices[1], vertices[2],
+                                       self.material(&mut fields, "material")?))
+            },
+            "mesh" => {
+                let (file, line) = fields.string("file")?;
+                let path = self.base_dir.join(&file);
+                let material = self.material(&mut fields, "material")?;
+
+                match TriangleMesh::load(&path.to_string_lossy(), material) {
+                    Ok(mesh) => Arc::new(mesh),
+                    Err(err) => return invalid(line, format!("could not load mesh \"{}\": {}",
+                                                             file, err))
+                }
+            },
+            "union" | "intersection" | "difference" => {
+                let left = self.use_shape(&mut fields, "left")?;
+                let right = self.use_shape(&mut fields, "right")?;
+
+                let mut csg = match &kind[..] {
+                    "union" => Csg::union(left, right),
+                    "intersection" => Csg::intersection(left, right),
+                    _ => Csg::difference(left, right)
+                };
+
+                if fields.has("material") {
+                    csg.set_material(self.material(&mut fields, "material")?);
+                }
+
+                Arc::new(csg)
+            },
+            "instance" => {
+                let mut instance = Instance::new(self.use_shape(&mut fields, "of")?,
+                                                 transform.take()
+                                                          .unwrap_or(Transform::identity()));
+
+                if fields.has("material") {
+                    instance.set_material(self.material(&mut fields, "material")?);
+                }
+
+                Arc::new(instance)
+            },
+            _ => return invalid(kind_line, format!("unknown shape type \"{}\"", kind))
+        };
+
+        let shape = match transform {
+            Some(transform) => Arc::new(Instance::new(shape, transform)),
+            None => shape
+        };
+        fields.finish()?;
+
+        if let Some((name, line)) = name {
+            if self.shapes.iter().any(|entry| entry.name.as_ref() == Some(&name)) {
+                return invalid(line, format!("shape \"{}\" is defined twice", name));
+            }
+
+            self.shapes.push(ShapeEntry {name: Some(name), shape: shape, used: false});
+        } else {
+            self.shapes.push(ShapeEntry {name: None, shape: shape, used: false});
+        }
+
+        Ok(())
+    }
+
+    fn add_light(&mut self, table: &Table) -> Result<(), LoadError> {
+        let mut fields = Fields::new(table);
+        let (kind, kind_line) = fields.string("type")?;
+
+        let light: Arc<Light> = match &kind[..] {
+            "point" => {
+                let mut light = PointLight::new(fields.vec3("position")?,
+                                                fields.color_or("color", Color::white())?);
+                light.set_intensity(fields.intensity()?);
+                light.set_attenuation(fields.attenuation()?);
+                if let Some(profile) = self.profile(&mut fields)? {
+                    let axis = fields.direction_or("axis", Vec3::new(0.0, -1.0, 0.0))?;
+                    light.set_profile(profile, axis);
+                }
+                Arc::new(light)
+            },
+            "spot" => {
+                let position = fields.vec3("position")?;
+                let direction = fields.direction("direction")?;
+                let outer = fields.number("outer_angle")?;
+                let inner = fields.number_or("inner_angle", outer)?;
+
+                if inner < 0.0 || inner > outer || outer > 180.0 {
+                    let line = fields.line(if outer > 180.0 { "outer_angle" }
+                                           else { "inner_angle" });
+                    return invalid(line, "a spot light needs 0 <= inner_angle <= outer_angle \
+                                          <= 180".to_string());
+                }
+
+                let mut light = SpotLight::new(position, direction, inner.to_radians(),
+                                               outer.to_radians(),
+                                               fields.color_or("color", Color::white())?);
+                light.set_intensity(fields.intensity()?);
+                light.set_attenuation(fields.attenuation()?);
+                if let Some(profile) = self.profile(&mut fields)? {
+                    light.set_profile(profile);
+                }
+                Arc::new(light)
+            },
+            "direction" => Arc::new(DirectionLight::new(fields.direction("direction")?,
+                                                        fields.color_or("color", Color::white())?)),
+            "rect" => {
+                let (u, v) = (fields.vec3("u")?, fields.vec3("v")?);
+                if (u % v).mag() == 0.0 {
+                    return invalid(fields.line("v"), "a rect light's u and v can't be zero or \
+                                                      parallel".to_string());
+                }
+
+                let mut light = RectLight::new(fields.vec3("center")?, u, v,
+                                               fields.color_or("color", Color::white())?);
+                if let Some(samples) = fields.optional_samples()? {
+                    light.set_samples(samples);
+                }
+                Arc::new(light)
+            },
+            "disk" => {
+                let mut light = DiskLight::new(fields.vec3("center")?, fields.direction("normal")?,
+                                               fields.positive_number("radius")?,
+                                               fields.color_or("color", Color::white())?);
+                if let Some(samples) = fields.optional_samples()? {
+                    light.set_samples(samples);
+                }
+                Arc::new(light)
+            },
+            "sphere" => {
+                let mut light = SphereLight::new(fields.vec3("center")?,
+                                                 fields.positive_number("radius")?,
+                                                 fields.color_or("color", Color::white())?);
+                if let Some(samples) = fields.optional_samples()? {
+                    light.set_samples(samples);
+                }
+                Arc::new(light)
+            },
+            _ => return invalid(kind_line, format!("unknown light type \"{}\"", kind))
+        };
+        fields.finish()?;
+
+        self.lights.push(light);
+        Ok(())
+    }
+
+    /// Loads the IES profile named by the optional "ies" key, relative to the scene file.
+    fn profile(&self, fields: &mut Fields) -> Result<Option<Arc<IesProfile>>, LoadError> {
+        let (file, line) = match fields.optional_string("ies")? {
+            Some(file) => file,
+            None => return Ok(None)
+        };
+        let path = self.base_dir.join(&file);
+
+        match IesProfile::load(&path.to_string_lossy()) {
+            Ok(profile) => Ok(Some(Arc::new(profile))),
+            Err(err) => invalid(line, format!("could not load IES profile \"{}\": {}",
+                                              file, err))
+        }
+    }
+
+    /// Looks up the material named by key.
+    fn material(&self, fields: &mut Fields, key: &str) -> Result<Arc<Material>, LoadError> {
+        let (name, line) = fields.string(key)?;
+
+        match self.materials.get(&name) {
+            Some(material) => Ok(material.clone()),
+            None => invalid(line, format!("material \"{}\" is not defined", name))
+        }
+    }
+
+    /// Looks up the shape named by key, and marks it as used by another shape.
+    fn use_shape(&mut self, fields: &mut Fields, key: &str) -> Result<Arc<Shape>, LoadError> {
+        let (name, line) = fields.string(key)?;
+
+        match self.shapes.iter_mut().find(|entry| entry.name.as_ref() == Some(&name)) {
+            Some(entry) => {
+                entry.used = true;
+                Ok(entry.shape.clone())
+            },
+            None => invalid(line, format!("shape \"{}\" is not defined", name))
+        }
+    }
+
+    /// Reads the optional scale (one uniform factor or one per axis), rotate (axis and angle in
+    /// degrees) and translate keys of a shape, applied in that order. Returns None if the shape
+    /// has none of them.
+    ///
+    /// Any shape with a transform is wrapped in an Instance; an "instance" shape uses it directly.
+    fn transform(&self, fields: &mut Fields) -> Result<Option<Transform>, LoadError> {
+        if !fields.has("scale") && !fields.has("rotate") && !fields.has("translate") {
+            return Ok(None);
+        }
+
+        let mut transform = Transform::identity();
+
+        if let Some(value) = fields.get("scale") {
+            let line = value.line;
+            let factors = match value.kind {
+                ValueKind::Number(n) => vec![n],
+                _ => as_numbers("scale", value)?
+            };
+            let factors = match factors.len() {
+                1 => Vec3::new(factors[0], factors[0], factors[0]),
+                3 => Vec3::new(factors[0], factors[1], factors[2]),
+                _ => return invalid(line, "scale needs 1 or 3 numbers".to_string())
+            };
+
+            if factors.x == 0.0 || factors.y == 0.0 || factors.z == 0.0 {
+                return invalid(line, "cannot scale by 0".to_string());
+            }
+
+            transform = transform.then(&Transform::scale(factors));
+        }
+
+        if fields.has("rotate") {
+            let (rotation, line) = fields.numbers("rotate")?;
+            if rotation.len() != 4 {
+                return invalid(line, "rotate needs an axis and an angle in degrees".to_string());
+            }
+
+            let axis = Vec3::new(rotation[0], rotation[1], rotation[2]);
+            if axis.x == 0.0 && axis.y == 0.0 && axis.z == 0.0 {
+                return invalid(line, "the axis to rotate around can't be [0, 0, 0]".to_string());
+            }
+            transform = transform.then(&Transform::rotation(axis, rotation[3].to_radians()));
+        }
+
+        if fields.has("translate") {
+            transform = transform.then(&Transform::translation(fields.vec3("translate")?));
+        }
+
+        Ok(Some(transform))
+    }
+}
+
+/// Fields reads typed values out of a Table, remembering which keys were read so that leftover
+/// (misspelled or unsupported) keys can be reported by finish().
+struct Fields<'a> {
+    table: &'a Table,
+    used: Vec<&'a str>
+}
+
+impl<'a> Fields<'a> {
+    fn new(table: &'a Table) -> Fields<'a> {
+        Fields {table: table, used: Vec::new()}
+    }
+
+    fn has(&self, key: &str) -> bool {
+        self.table.get(key).is_some()
+    }
+
+    fn get(&mut self, key: &str) -> Option<&'a Value> {
+        let table = self.table;
+        let entry = table.entries.iter().find(|entry| entry.0 == key);
+
+        entry.map(|entry| {
+            self.used.push(&entry.0);
+            &entry.1
+        })
+    }
+
+    /// Returns the line a key is on, for errors about its value. Keys which were left out (and
+    /// given a default) point at the table's header instead.
+    fn line(&self, key: &str) -> usize {
+        self.table.get(key).map_or(self.table.line, |value| value.line)
+    }
+
+    fn require(&mut self, key: &str) -> Result<&'a Value, LoadError> {
+        match self.get(key) {
+            Some(value) => Ok(value),
+            None => invalid(self.table.line,
+                            format!("[{}] is missing \"{}\"", self.table.name, key))
+        }
+    }
+
+    fn number(&mut self, key: &str) -> Result<f64, LoadError> {
+        let value = self.require(key)?;
+        as_number(key, value)
+    }
+
+    fn number_or(&mut self, key: &str, default: f64) -> Result<f64, LoadError> {
+        match self.get(key) {
+            Some(value) => as_number(key, value),
+            None => Ok(default)
+        }
+    }
+
+    fn optional_number(&mut self, key: &str) -> Result<Option<f64>, LoadError> {
+        match self.get(key) {
+            Some(value) => as_number(key, value).map(Some),
+            None => Ok(None)
+        }
+    }
+
+    /// Reads a number which must be above 0, like a size.
+    fn positive_number(&mut self, key: &str) -> Result<f64, LoadError> {
+        let value = self.require(key)?;
+        let number = as_number(key, value)?;
+
+        if number <= 0.0 {
+            return invalid(value.line, format!("\"{}\" must be positive", key));
+        }
+
+        Ok(number)
+    }
+
+    fn positive_integer(&mut self, key: &str) -> Result<i32, LoadError> {
+        let value = self.require(key)?;
+        let number = as_number(key, value)?;
+
+        if number < 1.0 || number.fract() != 0.0 || number > i32::max_value() as f64 {
+            return invalid(value.line, format!("\"{}\" must be a positive integer", key));
+        }
+
+        Ok(number as i32)
+    }
+
+    /// Reads the optional number of samples of an area light.
+    fn optional_samples(&mut self) -> Result<Option<u32>, LoadError> {
+        if self.has("samples") {
+            self.positive_integer("samples").map(|samples| Some(samples as u32))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Reads the optional intensity of a point or spot light, which defaults to 1.
+    fn intensity(&mut self) -> Result<f64, LoadError> {
+        let intensity = self.number_or("intensity", 1.0)?;
+
+        if intensity < 0.0 {
+            return invalid(self.line("intensity"),
+                           "a light's intensity can't be negative".to_string());
+        }
+
+        Ok(intensity)
+    }
+
+    /// Reads the optional strength of a glowing material, which scales its emission.
+    fn strength(&mut self) -> Result<f64, LoadError> {
+        let strength = self.number_or("strength", 1.0)?;
+
+        if strength < 0.0 {
+            return invalid(self.line("strength"),
+                           "a material's strength can't be negative".to_string());
+        }
+
+        Ok(strength)
+    }
+
+    /// Reads the weight of a mix material: either a number (0.5 by default) or a texture, which
+    /// is checker or noise with an optional scale and, for noise, number of octaves.
+    fn weight(&mut self) -> Result<Arc<Texture>, LoadError> {
+        let (kind, line) = match self.optional_string("texture")? {
+            Some(texture) => texture,
+            None => {
+                let weight = self.number_or("weight", 0.5)?;
+                if weight < 0.0 || weight > 1.0 {
+                    return invalid(self.line("weight"),
+                                   "a mix's weight must be between 0 and 1".to_string());
+                }
+                return Ok(Arc::new(weight));
+            }
+        };
+
+        let scale = self.number_or("scale", 1.0)?;
+        if scale <= 0.0 {
+            return invalid(self.line("scale"), "a texture's scale must be positive".to_string());
+        }
+
+        match &kind[..] {
+            "checker" => Ok(Arc::new(Checker::new(scale))),
+            "noise" => {
+                let octaves = if self.has("octaves") { self.positive_integer("octaves")? }
+                              else { 4 };
+                Ok(Arc::new(Noise::new(scale, octaves as u32)))
+            },
+            _ => invalid(line, format!("unknown texture \"{}\" (expected checker or noise)",
+                                       kind))
+        }
+    }
+
+    /// Reads the optional [constant, linear, quadratic] attenuation of a point or spot light,
+    /// which defaults to inverse square falloff.
+    fn attenuation(&mut self) -> Result<Attenuation, LoadError> {
+        if !self.has("attenuation") {
+            return Ok(Attenuation::inverse_square());
+        }
+
+        let (numbers, line) = self.numbers("attenuation")?;
+        let valid = numbers.len() == 3 && numbers.iter().all(|&n| n >= 0.0)
+                    && numbers.iter().any(|&n| n > 0.0);
+
+        if !valid {
+            return invalid(line, "\"attenuation\" must be 3 non-negative numbers [constant, \
+                                  linear, quadratic], not all zero".to_string());
+        }
+
+        Ok(Attenuation::new(numbers[0], numbers[1], numbers[2]))
+    }
+
+    fn boolean_or(&mut self, key: &str, default: bool) -> Result<bool, LoadError> {
+        match self.get(key) {
+            Some(&Value {kind: ValueKind::Boolean(b), ..}) => Ok(b),
+            Some(value) => invalid(value.line, format!("\"{}\" must be true or false", key)),
+            None => Ok(default)
+        }
+    }
+
+    /// Reads a string, and the line it is on.
+    fn string(&mut self, key: &str) -> Result<(String, usize), LoadError> {
+        let value = self.require(key)?;
+        as_string(key, value)
+    }
+
+    fn optional_string(&mut self, key: &str) -> Result<Option<(String, usize)>, LoadError> {
+        match self.get(key) {
+            Some(value) => as_string(key, value).map(Some),
+            None => Ok(None)
+        }
+    }
+
+    /// Reads an array of numbers, and the line it is on.
+    fn numbers(&mut self, key: &str) -> Result<(Vec<f64>, usize), LoadError> {
+        let value = self.require(key)?;
+        as_numbers(key, value).map(|numbers| (numbers, value.line))
+    }
+
+    fn vec3(&mut self, key: &str) -> Result<Vec3, LoadError> {
+        let value = self.require(key)?;
+        as_vec3(key, value)
+    }
+
+    fn vec3_or(&mut self, key: &str, default: Vec3) -> Result<Vec3, LoadError> {
+        match self.get(key) {
+            Some(value) => as_vec3(key, value),
+            None => Ok(default)
+        }
+    }
+
+    /// Reads a vector which is only used for its direction, so it can't be zero.
+    fn direction(&mut self, key: &str) -> Result<Vec3, LoadError> {
+        let value = self.require(key)?;
+        as_direction(key, value)
+    }
+
+    fn direction_or(&mut self, key: &str, default: Vec3) -> Result<Vec3, LoadError> {
+        match self.get(key) {
+            Some(value) => as_direction(key, value),
+            None => Ok(default)
+        }
+    }
+
+    /// Reads an array of 3-component arrays, and the line it is on.
+    fn vec3_list(&mut self, key: &str) -> Result<(Vec<Vec3>, usize), LoadError> {
+        let value = self.require(key)?;
+
+        match value.kind {
+            ValueKind::Array(ref values) => {
+                let mut vecs = Vec::new();
+                for v in values.iter() {
+                    vecs.push(as_vec3(key, v)?);
+                }
+                Ok((vecs, value.line))
+            },
+            _ => invalid(value.line, format!("\"{}\" must be an array of [x, y, z]", key))
+        }
+    }
+
+    fn color(&mut self, key: &str) -> Result<Color, LoadError> {
+        let v = self.vec3(key)?;
+        Ok(Color::new(v.x as f32, v.y as f32, v.z as f32))
+    }
+
+    fn color_or(&mut self, key: &str, default: Color) -> Result<Color, LoadError> {
+        if self.has(key) { self.color(key) } else { Ok(default) }
+    }
+
+    /// Fails if the table has any key which was never read.
+    fn finish(&self) -> Result<(), LoadError> {
+        for &(ref key, ref value) in self.table.entries.iter() {
+            if !self.used.contains(&&key[..]) {
+                return invalid(value.line,
+                               format!("unknown key \"{}\" in [{}]", key, self.table.name));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn as_number(key: &str, value: &Value) -> Result<f64, LoadError> {
+    match value.kind {
+        ValueKind::Number(n) => Ok(n),
+        _ => invalid(value.line, format!("\"{}\" must be a number", key))
+    }
+}
+
+fn as_string(key: &str, value: &Value) -> Result<(String, usize), LoadError> {
+    match value.kind {
+        ValueKind::String(ref s) => Ok((s.clone(), value.line)),
+        _ => invalid(value.line, format!("\"{}\" must be a string", key))
+    }
+}
+
+fn as_numbers(key: &str, value: &Value) -> Result<Vec<f64>, LoadError> {
+    let error = format!("\"{}\" must be an array of numbers", key);
+
+    match value.kind {
+        ValueKind::Array(ref values) => {
+            let mut numbers = Vec::new();
+            for v in values.iter() {
+                match v.kind {
+                    ValueKind::Number(n) => numbers.push(n),
+                    _ => return invalid(v.line, error)
+                }
+            }
+            Ok(numbers)
+        },
+        _ => invalid(value.line, error)
+    }
+}
+
+fn as_vec3(key: &str, value: &Value) -> Result<Vec3, LoadError> {
+    let numbers = as_numbers(key, value)?;
+
+    if numbers.len() != 3 {
+        return invalid(value.line, format!("\"{}\" must have 3 components", key));
+    }
+
+    Ok(Vec3::new(numbers[0], numbers[1], numbers[2]))
+}
+
+fn as_direction(key: &str, value: &Value) -> Result<Vec3, LoadError> {
+    let v = as_vec3(key, value)?;
+
+    if v.x == 0.0 && v.y == 0.0 && v.z == 0.0 {
+        return invalid(value.line, format!("\"{}\" must point somewhere, not be [0, 0, 0]", key));
+    }
+
+    Ok(v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use geom::Ray;
+
+    const CAMERA: &'static str = "\
+[camera]
+location = [0, 0, 5]
+look_at = [0, 0, 0]
+fov = 60
+width = 8
+height = 8
+";
+
+    /// Loads CAMERA followed by rest, which starts on line 7.
+    fn load(rest: &str) -> Result<(World, Camera), LoadError> {
+        parse_scene(&format!("{}{}", CAMERA, rest), Path::new(""))
+    }
+
+    fn assert_invalid(rest: &str, line: usize, message: &str) {
+        match load(rest) {
+            Err(LoadError::Invalid {line: l, message: ref m}) => {
+                assert_eq!((l, &m[..]), (line, message));
+            },
+            Err(err) => panic!("expected an invalid scene, found {}", err),
+            Ok(_) => panic!("expected an invalid scene, but it loaded")
+        }
+    }
+
+    #[test]
+    fn loads_a_scene() {
+        let (world, _) = load("\
+[world]
+background = [0.1, 0.2, 0.3]
+
+[[material]]
+name = \"red\"
+type = \"lambertian\"
+color = [1, 0, 0]
+
+[[shape]]
+type = \"sphere\"
+material = \"red\"
+center = [0, 0, 0]
+radius = 1
+
+[[light]]
+type = \"point\"
+position = [10, 10, 3]
+").unwrap();
+
+        assert_eq!(world.get_lights().len(), 1);
+
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = world.get_closest_intersection(&ray).expect("Cannot miss the sphere!");
+        assert!((hit.distance - 4.0).abs() < 1e-9);
+        let f = hit.material.eval(hit.norm, hit.norm, hit.norm, hit.position);
+        assert!(f.r > 0.0 && f.g == 0.0 && f.b == 0.0, "Cannot lose the sphere's material!");
+
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(world.get_closest_intersection(&ray).is_none());
+    }
+
+    #[test]
+    fn reports_unknown_shape_types() {
+        assert_invalid("\n[[shape]]\ntype = \"torus\"\nradius = 1\n", 9,
+                       "unknown shape type \"torus\"");
+    }
+
+    #[test]
+    fn reports_missing_fields() {
+        assert_invalid("\n[[shape]]\ntype = \"sphere\"\ncenter = [0, 0, 0]\nmaterial = \"red\"\n",
+                       8, "[shape] is missing \"radius\"");
+        assert_invalid("[[light]]\ntype = \"point\"\n", 7, "[light] is missing \"position\"");
+    }
+
+    #[test]
+    fn reports_undefined_materials() {
+        assert_invalid("[[shape]]\ntype = \"sphere\"\ncenter = [0, 0, 0]\nradius = 1\n\n\
+                        material = \"chrome\"\n", 12, "material \"chrome\" is not defined");
+    }
+
+    #[test]
+    fn reports_syntax_errors_with_their_line() {
+        assert_invalid("[world]\nbackground = [0.1, 0.2\n", 8, "unterminated array");
+    }
+}
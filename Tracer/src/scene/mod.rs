@@ -1,5 +1,23 @@
 pub mod camera;
 pub mod world;
+pub mod environment;
+pub mod sky;
+pub mod bvh;
+pub mod integrator;
+pub mod path_tracer;
+pub mod tiles;
+pub mod projection;
+pub mod observer;
+pub mod loader;
+mod toml;
 
 pub use scene::camera::Camera;
 pub use scene::world::World;
+pub use scene::environment::{Environment, EnvironmentMap};
+pub use scene::sky::Sky;
+pub use scene::bvh::BVH;
+pub use scene::integrator::{Integrator, Whitted};
+pub use scene::path_tracer::PathTracer;
+pub use scene::tiles::{Tile, TileOrder};
+pub use scene::projection::{FisheyeMapping, Projection};
+pub use scene::observer::{CancelToken, Progress, RenderObserver};
@@ -0,0 +1,64 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use geom::Color;
+use scene::tiles::Tile;
+
+/// How far along a render is.
+#[derive(Debug, Clone, Copy)]
+pub struct Progress {
+    pub tiles_done: usize,
+    pub tiles_total: usize,
+    /// The fraction of the image's pixels which have been traced, on [0, 1].
+    pub fraction: f64,
+    pub elapsed: Duration,
+    /// An estimate of how long is left, extrapolated from how fast pixels have been traced so
+    /// far.
+    pub remaining: Duration
+}
+
+impl Progress {
+    /// The percentage of the image which has been traced.
+    pub fn percent(&self) -> f64 {
+        self.fraction * 100.0
+    }
+}
+
+/// A RenderObserver watches an image being traced.
+///
+/// Its methods are called on the thread which called Camera::render, after each tile is done,
+/// so observers don't need to be shareable between threads. Both methods do nothing by default.
+pub trait RenderObserver {
+    /// Called with each tile as soon as it is traced, along with its colors row by row.
+    fn tile_completed(&mut self, _tile: &Tile, _colors: &[Color]) {}
+
+    /// Called after each tile with the progress of the whole image.
+    fn progress(&mut self, _progress: &Progress) {}
+}
+
+/// The unit type observes nothing, for renders nobody is watching.
+impl RenderObserver for () {}
+
+/// CancelToken stops a render early. Clones of a token share its state, so one can be handed
+/// to another thread (like a UI thread) which cancels the render while it is running.
+#[derive(Debug, Clone)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>
+}
+
+impl CancelToken {
+    /// Constructs a token which hasn't been cancelled.
+    pub fn new() -> CancelToken {
+        CancelToken {cancelled: Arc::new(AtomicBool::new(false))}
+    }
+
+    /// Asks every render using this token to stop as soon as possible.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
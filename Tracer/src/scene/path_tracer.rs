@@ -0,0 +1,136 @@
+use std::f64::INFINITY;
+use geom::Color;
+use geom::Ray;
+use geom::random::{jitter, power_heuristic};
+use scene::World;
+use scene::integrator::Integrator;
+
+/// The default number of bounces after which paths are cut off.
+const MAX_DEPTH: i32 = 16;
+
+/// The default number of bounces after which Russian roulette may end paths.
+const ROULETTE_DEPTH: i32 = 3;
+
+/// PathTracer is an unbiased Monte Carlo path tracer.
+///
+/// Each camera ray is extended into a path one bounce at a time. At every bounce, light is
+/// gathered straight from each light (next-event estimation), and the path continues in a
+/// direction sampled from the material's BSDF. Once a path is a few bounces long, Russian
+/// roulette randomly ends it with a chance based on how little light it could still carry, and
+/// boosts the paths that survive to make up for it.
+pub struct PathTracer {
+    max_depth: i32,
+    roulette_depth: i32
+}
+
+impl PathTracer {
+    pub fn new() -> PathTracer {
+        PathTracer {max_depth: MAX_DEPTH, roulette_depth: ROULETTE_DEPTH}
+    }
+
+    /// Sets the number of bounces after which a path is always ended.
+    pub fn set_max_depth(&mut self, max_depth: i32) {
+        self.max_depth = max_depth;
+    }
+
+    /// Sets the number of bounces after which Russian roulette starts ending paths.
+    pub fn set_roulette_depth(&mut self, roulette_depth: i32) {
+        self.roulette_depth = roulette_depth;
+    }
+}
+
+impl Integrator for PathTracer {
+    fn radiance(&self, world: &World, ray: &Ray) -> Color {
+        let mut color = Color::black();
+        // How much of the light arriving along the current ray makes it back to the camera.
+        let mut throughput = Color::white();
+        let mut ray = *ray;
+        let mut depth = 0;
+        // The pdf of the last bounce's direction, or None if it couldn't have been sampled any
+        // other way (from the camera, or off a perfect mirror).
+        let mut bounce_pdf = None;
+        let environment = world.get_environment();
+
+        loop {
+            let intersection = match world.get_closest_intersection(&ray) {
+                Some(intersection) => intersection,
+                None => {
+                    // The environment was also sampled directly at the last bounce.
+                    let weight = match bounce_pdf {
+                        Some(pdf) => power_heuristic(pdf, environment.pdf(ray.direction)),
+                        None => 1.0
+                    };
+                    color = color + throughput * environment.color(ray.direction) * weight;
+                    break;
+                }
+            };
+
+            // Light travelling through the inside of a material is partly absorbed on the way.
+            throughput = throughput * world.transmittance(&intersection, &ray);
+
+            // Glowing shapes which are lights were also sampled directly at the last bounce.
+            let weight = match (bounce_pdf, intersection.light.as_ref()) {
+                (Some(pdf), Some(light)) => power_heuristic(pdf, light.pdf(ray.origin,
+                                                                            &intersection)),
+                _ => 1.0
+            };
+            color = color + throughput * intersection.material.emission * weight;
+
+            let ref material = *intersection.material;
+            let wo = -ray.direction;
+            let norm = intersection.norm;
+            let position = intersection.position;
+
+            // Next-event estimation: add the light reaching this point straight from each light.
+            // Area lights are sampled once per bounce, since paths are already averaged.
+            for light in world.get_lights().iter() {
+                if let Some(sample) = light.in_shadow(world, &intersection) {
+                    let wi = sample.direction;
+                    let f = material.eval(wo, wi, norm, position);
+                    let weight = if light.is_visible() {
+                        power_heuristic(sample.pdf, material.pdf(wo, wi, norm, position))
+                    } else {
+                        1.0
+                    };
+                    color = color + throughput * f * sample.radiance
+                                    * ((wi * norm).abs() / sample.pdf * weight);
+                }
+            }
+
+            if let Some((wi, pdf)) = environment.sample() {
+                let f = material.eval(wo, wi, norm, position);
+                let shadow_ray = Ray::new(position, wi).step_epsilon();
+
+                if f.luminance() > 0.0 && !world.is_occluded(&shadow_ray, INFINITY) {
+                    let weight = power_heuristic(pdf, material.pdf(wo, wi, norm, position));
+                    color = color + throughput * f * environment.color(wi)
+                                    * ((wi * norm).abs() / pdf * weight);
+                }
+            }
+
+            if depth >= self.max_depth {
+                break;
+            }
+
+            let sample = match material.sample(wo, norm, position) {
+                Some(sample) => sample,
+                None => break
+            };
+
+            bounce_pdf = if sample.specular { None } else { Some(sample.pdf) };
+            throughput = throughput * sample.weight;
+            ray = Ray::new(position, sample.direction).step_epsilon();
+            depth += 1;
+
+            if depth > self.roulette_depth {
+                let survival = (throughput.r.max(throughput.g).max(throughput.b) as f64).min(0.95);
+                if jitter() >= survival {
+                    break;
+                }
+                throughput = throughput * (1.0 / survival);
+            }
+        }
+
+        color
+    }
+}
@@ -0,0 +1,88 @@
+use std::f64::consts::PI;
+
+use geom::Vec3;
+
+/// The ways a fisheye lens can map angles onto the image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FisheyeMapping {
+    /// The distance from the center of the image is proportional to the angle from the
+    /// forward direction, so angles are preserved along lines through the center.
+    Equidistant,
+    /// Equal areas in the image cover equal solid angles, so relative sizes are preserved.
+    Equisolid
+}
+
+/// Projection describes how a camera maps the pixels of its image onto rays.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    /// A regular pinhole camera, with a vertical field of vision in radians.
+    Perspective { fov: f64 },
+    /// Parallel rays through a rectangle, which keeps parallel lines parallel. height is the
+    /// height of the visible rectangle in world units.
+    Orthographic { height: f64 },
+    /// A fisheye lens covering fov radians (which may be up to 2π) across the height of the
+    /// image. Pixels outside of the image circle stay black.
+    Fisheye { fov: f64, mapping: FisheyeMapping },
+    /// A 360° panorama: longitude goes around the image horizontally and latitude vertically.
+    /// Images should be twice as wide as they are high.
+    Equirectangular
+}
+
+impl Projection {
+    /// Finds the ray through a point on the image, where u goes from -aspect / 2 on the left to
+    /// aspect / 2 on the right and v from -1/2 at the bottom to 1/2 at the top.
+    ///
+    /// The ray's origin (relative to the camera) and its normalized direction are returned in
+    /// the camera's frame, as (forward, up, right) vectors. Returns None if there is no ray
+    /// through the point, like outside of a fisheye's image circle.
+    pub fn local_ray(&self, u: f64, v: f64, aspect: f64) -> Option<(Vec3, Vec3)> {
+        let origin = Vec3::new(0.0, 0.0, 0.0);
+
+        match *self {
+            Projection::Perspective {fov} => {
+                let distance = 0.5 / (fov / 2.0).tan();
+                Some((origin, Vec3::new(distance, v, u).norm()))
+            },
+            Projection::Orthographic {height} => {
+                Some((Vec3::new(0.0, v * height, u * height), Vec3::new(1.0, 0.0, 0.0)))
+            },
+            Projection::Fisheye {fov, mapping} => {
+                let r = (u * u + v * v).sqrt();
+
+                // The image circle touches the top and bottom of the image, at r = 1/2, which has
+                // to be at fov / 2.
+                if r > 0.5 {
+                    return None;
+                }
+
+                let theta = match mapping {
+                    FisheyeMapping::Equidistant => r * fov,
+                    FisheyeMapping::Equisolid => 2.0 * (r * (fov / 4.0).sin() / 0.5).asin()
+                };
+
+                if r == 0.0 {
+                    return Some((origin, Vec3::new(1.0, 0.0, 0.0)));
+                }
+
+                let side = theta.sin() / r;
+                Some((origin, Vec3::new(theta.cos(), v * side, u * side)))
+            },
+            Projection::Equirectangular => {
+                let longitude = u / aspect * 2.0 * PI;
+                let latitude = v * PI;
+
+                Some((origin, Vec3::new(latitude.cos() * longitude.cos(),
+                                        latitude.sin(),
+                                        latitude.cos() * longitude.sin())))
+            }
+        }
+    }
+
+    /// Whether the projection's rays focus on a plane (rather than a sphere) around the camera.
+    pub fn has_focal_plane(&self) -> bool {
+        match *self {
+            Projection::Perspective {..} | Projection::Orthographic {..} => true,
+            Projection::Fisheye {..} | Projection::Equirectangular => false
+        }
+    }
+}
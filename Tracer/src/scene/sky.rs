@@ -0,0 +1,132 @@
+use std::f64::consts::PI;
+
+use geom::Vec3;
+use geom::Color;
+use shape::DirectionLight;
+
+/// Sky colors come out of the model in kcd/m², and the sun in klx. Scaling both by this makes a
+/// white surface lit by the noon sun and sky come out close to 1.
+const SCALE: f64 = 0.025;
+
+/// The illuminance of the sun before it passes through the atmosphere, in klx.
+const SUN_ILLUMINANCE: f64 = 128.0;
+
+/// Sky is the clear daytime sky of Preetham, Shirley and Smits' analytic model ("A Practical
+/// Analytic Model for Daylight", 1999), which gives the color of the sky in every direction
+/// from where the sun is and how hazy the air is.
+///
+/// The sky below the horizon is given the color of the horizon in the same compass direction.
+pub struct Sky {
+    sun: Vec3,
+    turbidity: f64,
+    /// The luminance (Y) and chromaticity (x, y) straight up.
+    zenith: [f64; 3],
+    /// The coefficients of the Perez distribution for each of Y, x and y.
+    perez: [[f64; 5]; 3]
+}
+
+impl Sky {
+    /// Constructs a sky with the sun in direction sun (from the ground towards the sun) and the
+    /// given turbidity, which is about 2 for a very clear sky and 10 for a hazy one.
+    pub fn new(sun: Vec3, turbidity: f64) -> Sky {
+        assert!(turbidity >= 1.0, "Cannot have a sky with a turbidity below 1!");
+
+        let sun = sun.norm();
+        let t = turbidity;
+        let theta = sun.y.max(0.0).min(1.0).acos();
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+
+        let polynomial = |c: [[f64; 4]; 3]| {
+            let row = |r: [f64; 4]| ((r[0] * theta + r[1]) * theta + r[2]) * theta + r[3];
+            t * t * row(c[0]) + t * row(c[1]) + row(c[2])
+        };
+        let zenith_x = polynomial([[0.00166, -0.00375, 0.00209, 0.0],
+                                   [-0.02903, 0.06377, -0.03202, 0.00394],
+                                   [0.11693, -0.21196, 0.06052, 0.25886]]);
+        let zenith_y = polynomial([[0.00275, -0.00610, 0.00317, 0.0],
+                                   [-0.04214, 0.08970, -0.04153, 0.00516],
+                                   [0.15346, -0.26756, 0.06670, 0.26688]]);
+
+        let perez = [[0.1787 * t - 1.4630, -0.3554 * t + 0.4275, -0.0227 * t + 5.3251,
+                      0.1206 * t - 2.5771, -0.0670 * t + 0.3703],
+                     [-0.0193 * t - 0.2592, -0.0665 * t + 0.0008, -0.0004 * t + 0.2125,
+                      -0.0641 * t - 0.8989, -0.0033 * t + 0.0452],
+                     [-0.0167 * t - 0.2608, -0.0950 * t + 0.0092, -0.0079 * t + 0.2102,
+                      -0.0441 * t - 1.6537, -0.0109 * t + 0.0529]];
+
+        Sky {sun: sun, turbidity: turbidity, zenith: [zenith_luminance, zenith_x, zenith_y],
+             perez: perez}
+    }
+
+    /// Returns the light arriving from the sky in (normalized) direction.
+    pub fn color(&self, direction: Vec3) -> Color {
+        let direction = if direction.y >= 0.0 {
+            direction
+        } else if direction.x == 0.0 && direction.z == 0.0 {
+            // Straight down is in every compass direction, so any point of the horizon will do.
+            Vec3::new(1.0, 0.0, 0.0)
+        } else {
+            Vec3::new(direction.x, 0.0, direction.z).norm()
+        };
+
+        let cos_theta = direction.y.max(0.001);
+        let cos_gamma = (direction * self.sun).max(-1.0).min(1.0);
+        let cos_theta_sun = self.sun.y.max(0.0).min(1.0);
+
+        // Each of Y, x and y is its value at the zenith scaled by the Perez distribution,
+        // relative to the distribution at the zenith.
+        let mut yxy = [0.0; 3];
+        for i in 0..3 {
+            yxy[i] = self.zenith[i] * perez(&self.perez[i], cos_theta, cos_gamma)
+                     / perez(&self.perez[i], 1.0, cos_theta_sun);
+        }
+
+        let (luminance, x, y) = (yxy[0], yxy[1], yxy[2]);
+        from_xyz(x / y * luminance, luminance, (1.0 - x - y) / y * luminance) * SCALE
+    }
+
+    /// Returns the light of the sun after it has passed through the atmosphere, to be used as
+    /// the color of a DirectionLight. This is black once the sun has set.
+    pub fn sun_color(&self) -> Color {
+        if self.sun.y <= 0.0 {
+            return Color::black();
+        }
+
+        // The relative length of the sun's path through the air (Kasten's formula).
+        let elevation = 90.0 - self.sun.y.acos().to_degrees();
+        let mass = 1.0 / (self.sun.y + 0.15 * (elevation + 3.885).powf(-1.253));
+
+        // Rayleigh scattering by air and Mie scattering by haze, using Ångström's formula for
+        // the haze, at a red, green and blue wavelength (in micrometers).
+        let beta = 0.04608 * self.turbidity - 0.04586;
+        let transmittance = |wavelength: f64| {
+            (-0.008735 * wavelength.powf(-4.08) * mass).exp()
+                * (-beta * wavelength.powf(-1.3) * mass).exp()
+        };
+
+        Color::new(transmittance(0.68) as f32, transmittance(0.55) as f32,
+                   transmittance(0.44) as f32) * (SUN_ILLUMINANCE * SCALE)
+    }
+
+    /// Returns a DirectionLight shining from the sun, with the color of sun_color.
+    pub fn sun_light(&self) -> DirectionLight {
+        DirectionLight::new(-self.sun, self.sun_color())
+    }
+}
+
+/// The Perez sky distribution at angle theta from the zenith and gamma from the sun.
+fn perez(c: &[f64; 5], cos_theta: f64, cos_gamma: f64) -> f64 {
+    let gamma = cos_gamma.acos();
+
+    (1.0 + c[0] * (c[1] / cos_theta).exp()) * (1.0 + c[2] * (c[3] * gamma).exp()
+                                              + c[4] * cos_gamma * cos_gamma)
+}
+
+/// Converts a CIE XYZ color to linear sRGB. Colors outside of sRGB's gamut are clipped.
+fn from_xyz(x: f64, y: f64, z: f64) -> Color {
+    Color::new((3.2406 * x - 1.5372 * y - 0.4986 * z).max(0.0) as f32,
+               (-0.9689 * x + 1.8758 * y + 0.0415 * z).max(0.0) as f32,
+               (0.0557 * x - 0.2040 * y + 1.0570 * z).max(0.0) as f32)
+}
@@ -0,0 +1,110 @@
+use std::cmp::Ordering;
+
+/// The orders in which the tiles of an image can be traced.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TileOrder {
+    /// Left to right, top to bottom, like reading a page.
+    Scanline,
+    /// Outwards from the center of the image, so the subject of the image shows up first.
+    Spiral,
+    /// Along a Hilbert curve, which keeps consecutive tiles next to each other.
+    Hilbert
+}
+
+/// A Tile is a rectangle of pixels which is traced as one piece of work.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tile {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32
+}
+
+impl Tile {
+    /// The number of pixels in the tile.
+    pub fn pixels(&self) -> usize {
+        (self.width * self.height) as usize
+    }
+}
+
+/// Splits an image into tiles of (at most) size by size pixels, listed in the given order.
+/// Tiles on the right and bottom edges are cut short to fit the image.
+pub fn split_image(width: i32, height: i32, size: i32, order: TileOrder) -> Vec<Tile> {
+    assert!(width > 0 && height > 0 && size > 0);
+
+    let columns = (width + size - 1) / size;
+    let rows = (height + size - 1) / size;
+
+    let tile = |column: i32, row: i32| {
+        let x = column * size;
+        let y = row * size;
+        Tile {x: x, y: y, width: size.min(width - x), height: size.min(height - y)}
+    };
+
+    match order {
+        TileOrder::Scanline => {
+            (0..rows).flat_map(|row| (0..columns).map(move |column| (column, row)))
+                     .map(|(column, row)| tile(column, row))
+                     .collect()
+        },
+        TileOrder::Spiral => {
+            let mut cells: Vec<(i32, i32)> =
+                (0..rows).flat_map(|row| (0..columns).map(move |column| (column, row)))
+                         .collect();
+
+            // Go ring by ring around the center tile, and around each ring by angle.
+            let center_x = (columns - 1) as f64 / 2.0;
+            let center_y = (rows - 1) as f64 / 2.0;
+            let key = |&(column, row): &(i32, i32)| {
+                let dx = column as f64 - center_x;
+                let dy = row as f64 - center_y;
+                (dx.abs().max(dy.abs()), dy.atan2(dx))
+            };
+
+            cells.sort_by(|a, b| key(a).partial_cmp(&key(b)).unwrap_or(Ordering::Equal));
+            cells.into_iter().map(|(column, row)| tile(column, row)).collect()
+        },
+        TileOrder::Hilbert => {
+            // Walk a curve covering the smallest power of two square containing the grid, and
+            // skip the cells which fall outside of it. The curve's length doesn't fit in an i32
+            // once a side has more than 32768 tiles.
+            let n = (columns.max(rows) as u64).next_power_of_two();
+
+            (0..n * n).map(|d| hilbert_cell(n, d))
+                      .filter(|&(column, row)| column < columns as u64 && row < rows as u64)
+                      .map(|(column, row)| tile(column as i32, row as i32))
+                      .collect()
+        }
+    }
+}
+
+/// Finds the cell at distance d along a Hilbert curve filling an n by n grid (n must be a power
+/// of two).
+fn hilbert_cell(n: u64, d: u64) -> (u64, u64) {
+    let (mut x, mut y) = (0, 0);
+    let mut t = d;
+    let mut s = 1;
+
+    while s < n {
+        let rx = 1 & (t / 2);
+        let ry = 1 & (t ^ rx);
+
+        // Rotate the quadrant so the curve lines up with the one before it.
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - x;
+                y = s - 1 - y;
+            }
+            let swap = x;
+            x = y;
+            y = swap;
+        }
+
+        x += s * rx;
+        y += s * ry;
+        t /= 4;
+        s *= 2;
+    }
+
+    (x, y)
+}
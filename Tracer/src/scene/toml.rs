@@ -0,0 +1,444 @@
+use std::fmt;
+
+/// A parse error, with the line it occurred on.
+#[derive(Debug, Clone)]
+pub struct TomlError {
+    pub line: usize,
+    pub message: String
+}
+
+impl fmt::Display for TomlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// The different kinds of values that can appear to the right of an "=".
+#[derive(Debug, Clone)]
+pub enum ValueKind {
+    String(String),
+    Number(f64),
+    Boolean(bool),
+    Array(Vec<Value>)
+}
+
+/// A value, and the line it starts on.
+#[derive(Debug, Clone)]
+pub struct Value {
+    pub kind: ValueKind,
+    pub line: usize
+}
+
+/// A table: its header name, the line of its header and its key/value pairs in file order. The
+/// keys written before the first header belong to a table with an empty name.
+#[derive(Debug, Clone)]
+pub struct Table {
+    pub name: String,
+    pub line: usize,
+    pub entries: Vec<(String, Value)>
+}
+
+impl Table {
+    /// Gets the value of a key, if the table has it.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.entries.iter().find(|entry| entry.0 == key).map(|entry| &entry.1)
+    }
+}
+
+/// Parses a whole document written in the subset of TOML used by Lux scene files into its list
+/// of tables, in file order.
+///
+/// Supported are comments, [table] and [[array of tables]] headers, and key = value pairs whose
+/// values are strings, numbers, booleans or (possibly nested, possibly multi-line) arrays. Dotted
+/// keys and inline tables are not supported. Every table and value remembers the line it was
+/// written on, so that errors found while building the scene can point back into the file.
+pub fn parse(source: &str) -> Result<Vec<Table>, TomlError> {
+    let mut parser = Parser {chars: source.chars().collect(), pos: 0, line: 1};
+    let mut tables = vec![Table {name: String::new(), line: 1, entries: Vec::new()}];
+
+    loop {
+        parser.skip_whitespace(true);
+
+        match parser.peek() {
+            None => break,
+            Some('[') => {
+                let line = parser.line;
+                let name = parser.parse_header()?;
+                tables.push(Table {name: name, line: line, entries: Vec::new()});
+            },
+            Some(_) => {
+                let line = parser.line;
+                let key = parser.parse_key()?;
+                parser.skip_whitespace(false);
+                parser.expect('=')?;
+                parser.skip_whitespace(false);
+                let value = parser.parse_value()?;
+
+                let table = tables.last_mut().unwrap();
+                if table.get(&key).is_some() {
+                    return Err(TomlError {line: line,
+                                          message: format!("duplicate key \"{}\"", key)});
+                }
+                table.entries.push((key, value));
+            }
+        }
+
+        parser.end_of_line()?;
+    }
+
+    Ok(tables)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        if let Some(c) = c {
+            self.pos += 1;
+            if c == '\n' {
+                self.line += 1;
+            }
+        }
+        c
+    }
+
+    fn error<T>(&self, message: String) -> Result<T, TomlError> {
+        Err(TomlError {line: self.line, message: message})
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), TomlError> {
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.next();
+                Ok(())
+            },
+            Some(c) if c != '\n' => self.error(format!("expected '{}', found '{}'", expected, c)),
+            _ => self.error(format!("expected '{}' before the end of the line", expected))
+        }
+    }
+
+    /// Skips spaces, tabs and comments, and newlines too if newlines is true.
+    fn skip_whitespace(&mut self, newlines: bool) {
+        while let Some(c) = self.peek() {
+            match c {
+                ' ' | '\t' | '\r' => { self.next(); },
+                '\n' if newlines => { self.next(); },
+                '#' => {
+                    while self.peek().map_or(false, |c| c != '\n') {
+                        self.next();
+                    }
+                },
+                _ => break
+            }
+        }
+    }
+
+    /// Checks that nothing but a comment follows on the current line.
+    fn end_of_line(&mut self) -> Result<(), TomlError> {
+        self.skip_whitespace(false);
+
+        match self.peek() {
+            None | Some('\n') => Ok(()),
+            Some(c) => self.error(format!("unexpected '{}' after value", c))
+        }
+    }
+
+    fn parse_header(&mut self) -> Result<String, TomlError> {
+        self.expect('[')?;
+        let array = self.peek() == Some('[');
+        if array {
+            self.next();
+        }
+
+        self.skip_whitespace(false);
+        let name = self.parse_key()?;
+        self.skip_whitespace(false);
+
+        self.expect(']')?;
+        if array {
+            self.expect(']')?;
+        }
+
+        Ok(name)
+    }
+
+    fn parse_key(&mut self) -> Result<String, TomlError> {
+        let mut key = String::new();
+
+        while let Some(c) = self.peek() {
+            if c.is_alphanumeric() || c == '_' || c == '-' {
+                key.push(c);
+                self.next();
+            } else {
+                break;
+            }
+        }
+
+        if key.is_empty() {
+            match self.peek() {
+                Some(c) if c != '\n' => self.error(format!("expected a key, found '{}'", c)),
+                _ => self.error("expected a key".to_string())
+            }
+        } else {
+            Ok(key)
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Value, TomlError> {
+        let line = self.line;
+
+        let kind = match self.peek() {
+            Some('"') => ValueKind::String(self.parse_string()?),
+            Some('[') => ValueKind::Array(self.parse_array()?),
+            Some(c) if c.is_alphanumeric() || c == '+' || c == '-' || c == '.' => {
+                let mut word = String::new();
+                while let Some(c) = self.peek() {
+                    if c.is_alphanumeric() || c == '+' || c == '-' || c == '.' || c == '_' {
+                        word.push(c);
+                        self.next();
+                    } else {
+                        break;
+                    }
+                }
+
+                match &word[..] {
+                    "true" => ValueKind::Boolean(true),
+                    "false" => ValueKind::Boolean(false),
+                    _ => match word.replace('_', "").parse::<f64>() {
+                        // Rust also reads "inf" and "nan", and too many digits become infinite,
+                        // none of which make sense anywhere in a scene.
+                        Ok(number) if number.is_finite() => ValueKind::Number(number),
+                        Ok(_) => return self.error(format!("\"{}\" isn't a finite number", word)),
+                        Err(_) => return self.error(format!("invalid value \"{}\"", word))
+                    }
+                }
+            },
+            _ => return self.error("expected a value".to_string())
+        };
+
+        Ok(Value {kind: kind, line: line})
+    }
+
+    fn parse_string(&mut self) -> Result<String, TomlError> {
+        self.expect('"')?;
+        let mut string = String::new();
+
+        loop {
+            match self.peek() {
+                Some('\n') | None => return self.error("unterminated string".to_string()),
+                _ => {}
+            }
+
+            match self.next().unwrap() {
+                '"' => return Ok(string),
+                '\\' => match self.next() {
+                    Some('n') => string.push('\n'),
+                    Some('t') => string.push('\t'),
+                    Some('"') => string.push('"'),
+                    Some('\\') => string.push('\\'),
+                    _ => return self.error("invalid escape sequence in string".to_string())
+                },
+                c => string.push(c)
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Vec<Value>, TomlError> {
+        let start_line = self.line;
+        self.expect('[')?;
+        let mut values = Vec::new();
+
+        loop {
+            self.skip_whitespace(true);
+
+            match self.peek() {
+                Some(']') => {
+                    self.next();
+                    return Ok(values);
+                },
+                None => {
+                    return Err(TomlError {line: start_line,
+                                          message: "unterminated array".to_string()});
+                },
+                _ => {}
+            }
+
+            values.push(self.parse_value()?);
+            self.skip_whitespace(true);
+
+            match self.peek() {
+                Some(',') => { self.next(); },
+                Some(']') => {},
+                None => {
+                    return Err(TomlError {line: start_line,
+                                          message: "unterminated array".to_string()});
+                },
+                Some(c) => return self.error(format!("expected ',' or ']', found '{}'", c))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(value: &Value) -> f64 {
+        match value.kind {
+            ValueKind::Number(number) => number,
+            ref kind => panic!("expected a number, found {:?}", kind)
+        }
+    }
+
+    fn error(source: &str) -> TomlError {
+        match parse(source) {
+            Ok(tables) => panic!("expected an error, found {:?}", tables),
+            Err(error) => error
+        }
+    }
+
+    fn assert_error(source: &str, line: usize, message: &str) {
+        let error = error(source);
+        assert_eq!((error.line, &error.message[..]), (line, message));
+    }
+
+    #[test]
+    fn parses_tables_in_file_order() {
+        let tables = parse("width = 640\n\n[camera]\nfov = 60\n\n[[light]]\n[[light]]\n")
+                         .unwrap();
+
+        let names: Vec<_> = tables.iter().map(|table| (&table.name[..], table.line)).collect();
+        assert_eq!(names, [("", 1), ("camera", 3), ("light", 6), ("light", 7)]);
+        assert_eq!(number(tables[0].get("width").unwrap()), 640.0);
+        assert_eq!(number(tables[1].get("fov").unwrap()), 60.0);
+        assert!(tables[1].get("width").is_none());
+        assert!(tables[2].entries.is_empty());
+    }
+
+    #[test]
+    fn parses_values() {
+        let source = "a = \"say \\\"hi\\\"\\n\\tback\\\\slash\"\n\
+                      b = true\n\
+                      c = false\n\
+                      d = -1_000.5\n\
+                      e = +2.5e-3\n\
+                      f = .5\n";
+        let tables = parse(source).unwrap();
+        let table = &tables[0];
+
+        match table.get("a").unwrap().kind {
+            ValueKind::String(ref string) => assert_eq!(string, "say \"hi\"\n\tback\\slash"),
+            ref kind => panic!("expected a string, found {:?}", kind)
+        }
+        match (&table.get("b").unwrap().kind, &table.get("c").unwrap().kind) {
+            (&ValueKind::Boolean(true), &ValueKind::Boolean(false)) => {},
+            kinds => panic!("expected true and false, found {:?}", kinds)
+        }
+        assert_eq!(number(table.get("d").unwrap()), -1000.5);
+        assert_eq!(number(table.get("e").unwrap()), 0.0025);
+        assert_eq!(number(table.get("f").unwrap()), 0.5);
+    }
+
+    #[test]
+    fn parses_nested_multi_line_arrays() {
+        let source = "[mesh]\n\
+                      points = [\n  [0, 1, 2], # first\n\n  [3, [4]],\n]\n\
+                      empty = []\n";
+        let tables = parse(source).unwrap();
+        let points = tables[1].get("points").unwrap();
+        assert_eq!(points.line, 2);
+
+        let rows = match points.kind {
+            ValueKind::Array(ref rows) => rows,
+            ref kind => panic!("expected an array, found {:?}", kind)
+        };
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].line, 5);
+        match rows[1].kind {
+            ValueKind::Array(ref row) => {
+                assert_eq!(number(&row[0]), 3.0);
+                match row[1].kind {
+                    ValueKind::Array(ref inner) => assert_eq!(number(&inner[0]), 4.0),
+                    ref kind => panic!("expected an array, found {:?}", kind)
+                }
+            },
+            ref kind => panic!("expected an array, found {:?}", kind)
+        }
+
+        match tables[1].get("empty").unwrap().kind {
+            ValueKind::Array(ref values) => assert!(values.is_empty()),
+            ref kind => panic!("expected an array, found {:?}", kind)
+        }
+    }
+
+    #[test]
+    fn skips_comments_and_blank_lines() {
+        let tables = parse("# a scene\n\n  [ world ] # the world\r\n\tkey=1#one\n# end").unwrap();
+
+        assert_eq!(tables.len(), 2);
+        assert_eq!(tables[1].name, "world");
+        let value = tables[1].get("key").unwrap();
+        assert_eq!((number(value), value.line), (1.0, 4));
+    }
+
+    #[test]
+    fn rejects_duplicate_keys() {
+        assert_error("[camera]\nfov = 60\nfov = 90\n", 3, "duplicate key \"fov\"");
+    }
+
+    #[test]
+    fn rejects_malformed_headers() {
+        assert_error("[camera\n", 1, "expected ']' before the end of the line");
+        assert_error("[camera)\n", 1, "expected ']', found ')'");
+        assert_error("[[light]\n", 1, "expected ']' before the end of the line");
+        assert_error("[]\n", 1, "expected a key, found ']'");
+    }
+
+    #[test]
+    fn rejects_malformed_keys() {
+        assert_error("\n= 1\n", 2, "expected a key, found '='");
+        assert_error("fov 60\n", 1, "expected '=', found '6'");
+        assert_error("fov\n", 1, "expected '=' before the end of the line");
+    }
+
+    #[test]
+    fn rejects_malformed_values() {
+        assert_error("fov =\n", 1, "expected a value");
+        assert_error("fov = ,\n", 1, "expected a value");
+        assert_error("fov = 6o\n", 1, "invalid value \"6o\"");
+        assert_error("fov = 60 90\n", 1, "unexpected '9' after value");
+        assert_error("fov = [60, 90] x\n", 1, "unexpected 'x' after value");
+    }
+
+    #[test]
+    fn rejects_numbers_which_are_not_finite() {
+        assert_error("fov = inf\n", 1, "\"inf\" isn't a finite number");
+        assert_error("fov = -inf\n", 1, "\"-inf\" isn't a finite number");
+        assert_error("fov = nan\n", 1, "\"nan\" isn't a finite number");
+        assert_error("fov = 1e999\n", 1, "\"1e999\" isn't a finite number");
+        assert_error("\nfov = [1, NaN]\n", 2, "\"NaN\" isn't a finite number");
+    }
+
+    #[test]
+    fn rejects_malformed_strings() {
+        assert_error("name = \"open\nnext = 1\n", 1, "unterminated string");
+        assert_error("name = \"open", 1, "unterminated string");
+        assert_error("name = \"bad \\q\"\n", 1, "invalid escape sequence in string");
+    }
+
+    #[test]
+    fn rejects_malformed_arrays() {
+        assert_error("\npoints = [1, 2,\n 3\n", 2, "unterminated array");
+        assert_error("points = [1, 2", 1, "unterminated array");
+        assert_error("points = [\n1\n2]\n", 3, "expected ',' or ']', found '2'");
+    }
+}
@@ -3,29 +3,74 @@ use std::f64::INFINITY;
 use geom::Color;
 use geom::Ray;
 use geom::Vec3;
+use geom::AABB;
+use geom::random::power_heuristic;
 use shape::Intersection;
 use shape::Shape;
 use shape::Light;
+use shape::ShapeLight;
+use scene::BVH;
+use scene::Environment;
 
-/// The maximum recursive iterations that can be attained by the tracer.
+/// The default maximum recursive iterations that can be attained by the tracer.
 const MAX_ITER: i32 = 6;
 
 /// The World struct represents all of the objects in the scene that will be traced by the Camera.
+///
+/// Shapes with a bounding box are kept in a BVH so that finding the closest intersection doesn't
+/// require testing every shape. Unbounded shapes (like Planes) can't be put in a BVH, so they are
+/// kept in a separate list and always tested.
 pub struct World {
     objects: Vec<Arc<Shape>>,
+    /// The light sampling each of objects, for the glowing ones which are sampled as lights.
+    object_lights: Vec<Option<Arc<ShapeLight>>>,
+    unbounded: Vec<Arc<Shape>>,
+    bvh: BVH,
     lights: Vec<Arc<Light>>,
-    bg_color: Color
+    environment: Environment,
+    max_depth: i32
 }
 
 impl World {
-    /// Constructs an empty world.
+    /// Constructs an empty world, with the same background color in every direction.
     pub fn new(bg_color: Color) -> World {
-        World {objects: Vec::new(), lights: Vec::new(), bg_color: bg_color}
+        World {objects: Vec::new(), object_lights: Vec::new(), unbounded: Vec::new(),
+               bvh: BVH::new(&[]), lights: Vec::new(),
+               environment: Environment::Constant(bg_color),
+               max_depth: MAX_ITER}
+    }
+
+    /// Replaces the environment, which lights the world from far away and is seen by rays
+    /// that don't hit anything.
+    pub fn set_environment(&mut self, environment: Environment) {
+        self.environment = environment;
+    }
+
+    /// Sets the maximum recursive iterations (reflections, refractions and diffuse bounces)
+    /// that a traced ray may go through.
+    pub fn set_max_depth(&mut self, max_depth: i32) {
+        self.max_depth = max_depth;
     }
 
     /// Adds a shape to the world.
+    ///
+    /// Shapes added after the last call to build_bvh are still found, but are tested one by one,
+    /// so build_bvh should be called once the scene is complete.
     pub fn add_shape(&mut self, shape: Arc<Shape>) {
-        self.objects.push(shape);
+        if shape.bounding_box().is_some() {
+            self.objects.push(shape);
+            self.object_lights.push(None);
+        } else {
+            self.unbounded.push(shape);
+        }
+    }
+
+    /// (Re)builds the BVH over every bounded shape in the world.
+    pub fn build_bvh(&mut self) {
+        let bounds: Vec<AABB> = self.objects.iter()
+                                            .map(|obj| obj.bounding_box().unwrap())
+                                            .collect();
+        self.bvh = BVH::new(&bounds);
     }
 
     /// Adds a PointLight to the world.
@@ -33,212 +78,210 @@ impl World {
         self.lights.push(light);
     }
 
+    /// Adds a glowing shape to the world, and a ShapeLight for it so that it lights the world
+    /// like any other light. Shapes which can't be sampled (like planes) are still seen glowing,
+    /// but don't cast any light.
+    ///
+    /// Intersections with the shape carry its light, so that integrators know its light was
+    /// already sampled. Other shapes sharing its material aren't affected.
+    pub fn add_emitter(&mut self, shape: Arc<Shape>) {
+        // Checking by sampling once is fine here, since emitters are only added while the scene
+        // is being built.
+        if shape.bounding_box().is_none() || shape.sample_surface().is_none() {
+            self.add_shape(shape);
+            return;
+        }
+
+        let light = Arc::new(ShapeLight::new(shape.clone()));
+        self.lights.push(light.clone());
+        self.objects.push(shape);
+        self.object_lights.push(Some(light));
+    }
+
+    /// Returns the lights in the world.
+    pub fn get_lights(&self) -> &[Arc<Light>] {
+        &self.lights
+    }
+
+    /// Returns the environment seen along rays which don't hit anything.
+    pub fn get_environment(&self) -> &Environment {
+        &self.environment
+    }
+
     /// Returns the closest intersection to a ray.
     pub fn get_closest_intersection(&self, ray: &Ray) -> Option<Intersection> {
-        let mut distance = INFINITY;
-        let mut closest: Option<Intersection> = None;
-
-        for obj in self.objects.iter() {
-            if let Some(intersection) = obj.intersect_first(*ray) {
-                if intersection.distance < distance {
-                    distance = intersection.distance;
-                    closest = Some(intersection);
-                }
+        let intersect = |i: usize| {
+            self.objects[i].intersect_first(*ray).map(|mut intersection| {
+                intersection.light = self.object_lights[i].clone();
+                intersection
+            })
+        };
+
+        let mut closest = self.bvh.intersect_first(ray, &intersect);
+        let mut distance = closest.as_ref().map_or(INFINITY, |c| c.distance);
+
+        let remaining = (self.bvh.len()..self.objects.len()).filter_map(&intersect)
+                        .chain(self.unbounded.iter().filter_map(|obj| obj.intersect_first(*ray)));
+
+        for intersection in remaining {
+            if intersection.distance < distance {
+                distance = intersection.distance;
+                closest = Some(intersection);
             }
         }
 
         closest
     }
 
+    /// Returns whether anything in the world blocks the ray closer than max_distance.
+    ///
+    /// This is meant for shadow rays, so it returns as soon as any blocker is found rather than
+    /// searching for the closest one.
+    pub fn is_occluded(&self, ray: &Ray, max_distance: f64) -> bool {
+        let blocks = |obj: &Arc<Shape>| {
+            obj.intersect_first(*ray).map_or(false, |i| i.distance < max_distance)
+        };
+
+        self.bvh.intersect_any(ray, max_distance, |i| self.objects[i].intersect_first(*ray))
+            || self.objects[self.bvh.len()..].iter().any(&blocks)
+            || self.unbounded.iter().any(&blocks)
+    }
+
     /// Traces a single ray and returns the intensity of light that is emitted through the ray.
     pub fn trace_ray(&self, ray: &Ray, depth: i32) -> Color {
-        if depth > MAX_ITER {
+        if depth > self.max_depth {
             return Color::black();
         }
 
         let closest = self.get_closest_intersection(ray);
 
         match closest {
-            None => {
-                //TODO: background color
-                self.bg_color
-            },
+            None => self.environment.color(ray.direction),
             Some(intersection) => {
-                let mut final_color = Color::black();
-
-                // We add the contributing color of each light
-                for light in self.lights.iter() {
-                    if let Some(light_direction) = light.in_shadow(self, &intersection) {
-                        let illum_color = self.light_color(&intersection, ray, &light_direction);
-                        final_color = final_color + light.get_color() * illum_color;
-                    }
-                }
-
-                let color = final_color + self.refl_color(&intersection, ray, depth)
-                            + self.bg_color(&intersection, ray, depth)
-                            + self.trans_color(&intersection, ray, depth);
-
-                if color.b < 0.0 {
-                    print!("{}, {}", intersection.position, depth);
-                }
-
-                color
+                let color = intersection.material.emission + self.shade(&intersection, ray, depth);
+                color * self.transmittance(&intersection, ray)
             }
         }
     }
 
-    /// Returns the color due to direct illumination of an object by lights.
-    pub fn light_color(&self, intersection: &Intersection, ray: &Ray, light_direction: &Vec3)
-                       -> Color {
-        // We can get a temporary (borrowed) reference to the material by the "ref" keyword
-        let ref material = *intersection.material;
-
-        // We calculate the Lambertian BRDF by taking the cosine of the light and the normal
-        let cos = (light_direction.norm() * intersection.norm).max(0.0);
-        let matte_illum = material.matte_color * material.matte_intensity * cos;
-
-        let Dr = -*light_direction + (*light_direction).project(intersection.norm) * 2.0;
-
-        let gloss = if material.glossy_power == -1.0 { 10000.0 } else { material.glossy_power };
-
-        let spec_illum = material.glossy_color *
-                         (Dr * -ray.direction).max(0.0).powf(gloss) *
-                         material.glossy_intensity
-                       + material.trans_color *
-                         (Dr * -ray.direction).max(0.0).powi(10000) *
-                         material.trans_intensity;
-
-        matte_illum + spec_illum
+    /// Returns the fraction of light which is left after travelling along ray to intersection.
+    /// This is only less than 1 for rays inside of a material which absorbs light, which are
+    /// the ones hitting the back of a surface.
+    pub fn transmittance(&self, intersection: &Intersection, ray: &Ray) -> Color {
+        if ray.direction * intersection.norm > 0.0 {
+            intersection.material.transmittance(intersection.distance)
+        } else {
+            Color::white()
+        }
     }
 
-    /// Returns the color due to specular reflection from other objects.
-    pub fn refl_color(&self, intersection: &Intersection, ray: &Ray, depth: i32) -> Color {
-        // We can get a temporary (borrowed) reference to the material by the "ref" keyword
-        let ref material = *intersection.material;
+    /// Returns the light leaving an intersection back along the ray which found it, other than
+    /// the light the surface gives off itself.
+    fn shade(&self, intersection: &Intersection, ray: &Ray, depth: i32) -> Color {
+        let mut final_color = Color::black();
+
+        // We add the contributing color of each light, averaged over its samples
+        for light in self.lights.iter() {
+            let samples = light.samples();
+            let mut light_color = Color::black();
+
+            for _ in 0..samples {
+                if let Some(sample) = light.in_shadow(self, intersection) {
+                    let illum_color = self.light_color(intersection, ray, &sample.direction);
+                    // Glowing shapes can also be found by the sampled bounce.
+                    let weight = if light.is_visible() {
+                        let bsdf_pdf = intersection.material.pdf(-ray.direction, sample.direction,
+                                                                 intersection.norm,
+                                                                 intersection.position);
+                        power_heuristic(sample.pdf * samples as f64, bsdf_pdf)
+                    } else {
+                        1.0
+                    };
+                    light_color = light_color + sample.radiance * illum_color
+                                                * (weight / sample.pdf);
+                }
+            }
 
-        // If there is no glossy intensity, then quit early.
-        if material.glossy_intensity == 0.0 {
-            return Color::black();
+            final_color = final_color + light_color * (1.0 / samples as f64);
         }
 
-        // The ray that represents the perfect (mirror) reflection
-        let perfect_reflect = (ray.direction + (-ray.direction).project(intersection.norm)
-                               * 2.0).norm();
+        final_color + self.indirect_color(intersection, ray, depth)
+    }
 
-        // glossy_power == -1 represents a perfect mirror.
-        if material.glossy_power == -1.0 {
-            let reflected_ray = Ray::new(intersection.position, perfect_reflect).step_epsilon();
+    /// Returns the fraction of the light arriving from light_direction which the surface
+    /// scatters back along the ray, times the cosine of the light with the normal.
+    pub fn light_color(&self, intersection: &Intersection, ray: &Ray, light_direction: &Vec3)
+                       -> Color {
+        let wi = light_direction.norm();
+        let f = intersection.material.eval(-ray.direction, wi, intersection.norm,
+                                           intersection.position);
 
-            material.glossy_color * self.trace_ray(&reflected_ray, depth+1)
-                                  * material.glossy_intensity
-        } else {
-            // Construct a orthonormal coordinate system aligned upwards to the surface normal
-            let w = (perfect_reflect).norm(); //up
-            let u = (Vec3::up() % w).norm();
-            let v = u % w;
-
-            let sample = Vec3::sample_hemisphere(material.glossy_power);
-            let Dr = u * sample.x + w * sample.y + v * sample.z;
-
-            // This is a bit of a convoluted line, but it keeps me from having to use "mut"... We
-            // check if the new emitted ray is "under the surface" of the object. If so, we rotate
-            // it around its y axis 180 degrees and return the new outward facing vector.
-            let reflected_direction = (if Dr * intersection.norm < 0.0 {
-                u * -sample.x + w * sample.y + v * -sample.z
-            } else {
-                Dr
-            }).norm();
-
-            let reflected_ray = Ray::new(intersection.position,
-                                         reflected_direction).step_epsilon();
-
-            material.glossy_color * self.trace_ray(&reflected_ray, depth+1)
-                                  * material.glossy_intensity
-        }
+        f * (wi * intersection.norm).abs()
     }
 
-    pub fn trans_color(&self, intersection: &Intersection, ray: &Ray, depth: i32) -> Color {
-        // We can get a temporary (borrowed) reference to the material by the "ref" keyword
-        let ref material = *intersection.material;
-
-        // If there is no transmissive intensity, then quit early.
-        if material.trans_intensity == 0.0 {
+    /// Returns the color due to light from other objects (and the environment) scattered by
+    /// the surface, by tracing one ray sampled from its material.
+    ///
+    /// When the environment can be sampled by brightness, a second sample is taken towards
+    /// the bright parts of it, and both are weighed by multiple importance sampling.
+    pub fn indirect_color(&self, intersection: &Intersection, ray: &Ray, depth: i32) -> Color {
+        if depth + 1 > self.max_depth {
             return Color::black();
         }
 
-        // Calculate ray that represents a perfect reflection
-        let perfect_reflect = (ray.direction + (-ray.direction).project(intersection.norm)
-                               * 2.0).norm();
-        let reflected_ray = Ray::new(intersection.position, perfect_reflect).step_epsilon();
-
-        //if self.tir(intersection, ray) {
-        //        material.trans_color * self.trace_ray(&reflected_ray, depth+1)
-        //} else {
-            let cos_theta = -(intersection.norm * ray.direction);
-
-            if cos_theta > 0.0 {
-                let eta = material.trans_eta;
-                let angle = cos_theta;
-                let norm = intersection.norm;
-
-                let angle2 = (1.0 - (1.0 - angle * angle) / (eta * eta)).sqrt();
-                let wt = ray.direction / eta - norm * (angle2 - angle / eta);
+        let ref material = intersection.material;
+        let position = intersection.position;
+        let wo = -ray.direction;
+        let norm = intersection.norm;
+        let mut color = Color::black();
 
-                let transmitted_ray = Ray::new(intersection.position, wt).step_epsilon();
+        if let Some((wi, env_pdf)) = self.environment.sample() {
+            let f = material.eval(wo, wi, norm, position);
+            let env_ray = Ray::new(position, wi).step_epsilon();
 
-                material.trans_color * self.trace_ray(&transmitted_ray, depth+1)
-                                     * material.trans_intensity
-                                     * (1.0 / (eta * eta))
-            } else {
-                let eta = 1.0 / material.trans_eta;
-                let angle = -cos_theta;
-                let norm = -intersection.norm;
-
-                let angle2 = (1.0 - (1.0 - angle * angle) / (eta * eta)).sqrt();
-                let wt = ray.direction / eta - norm * (angle2 - angle / eta);
-
-                let transmitted_ray = Ray::new(intersection.position, wt).step_epsilon();
-
-                material.trans_color * self.trace_ray(&transmitted_ray, depth+1)
-                                     * material.trans_intensity
-                                     * (1.0 / (eta * eta))
-        //    }
+            if f.luminance() > 0.0 && !self.is_occluded(&env_ray, INFINITY) {
+                let weight = power_heuristic(env_pdf, material.pdf(wo, wi, norm, position));
+                color = color + self.environment.color(wi) * f
+                                * ((wi * norm).abs() / env_pdf * weight);
+            }
         }
-    }
-
-    /// Checks whether there is total internal reflection
-    fn tir(&self, intersection: &Intersection, ray: &Ray) -> bool {
-        let ref material = *intersection.material;
-        let cos_theta = -(intersection.norm * ray.direction);
-        let eta = if cos_theta >= 0.0 { material.trans_eta } else { 1.0 / material.trans_eta };
-
-        return 1.0 - (1.0 - cos_theta * cos_theta) / (eta * eta) < 0.0;
-    }
 
-    /// Returns the color due to diffuse reflection from other objects
-    pub fn bg_color(&self, intersection: &Intersection, ray: &Ray, depth: i32) -> Color {
-        let ref material = *intersection.material;
+        let sample = match material.sample(wo, norm, position) {
+            Some(sample) => sample,
+            None => return color
+        };
+        let bounce_ray = Ray::new(position, sample.direction).step_epsilon();
 
-        // We can quit early if there's no matte_intensity
-        if material.matte_intensity == 0.0 {
-            return Color::black();
-        }
-
-        // Sample a hemisphere vector and construct a basis centered around the norm
-        let sample = Vec3::sample_hemisphere(0.0);
-        let w = intersection.norm; //up
-        let v = (w % Vec3::up()).norm();
-        let u = v % w;
+        // Perfectly specular bounces couldn't have found any light by sampling, so they see
+        // everything. Other bounces leave out whatever was already sampled.
+        let bounce_color = if sample.specular {
+            self.trace_ray(&bounce_ray, depth+1)
+        } else {
+            match self.get_closest_intersection(&bounce_ray) {
+                Some(hit) => self.bounce_color(&hit, &bounce_ray, depth+1, sample.pdf),
+                None => self.environment.color(sample.direction)
+                        * power_heuristic(sample.pdf, self.environment.pdf(sample.direction))
+            }
+        };
 
-        let shadow_direction = u * sample.x + w * sample.y + v * sample.z;
-        let shadow_ray = Ray::new(intersection.position, shadow_direction).step_epsilon();
+        color + bounce_color * sample.weight
+    }
 
-        let shadow_color = self.trace_ray(&shadow_ray, depth+1);
-        // We can calculate a cosine of the normal and the shadow direction because the shadow
-        // technically is emitted by a "light" which is just the other object emitting diffuse
-        // light.
-        let cos = (shadow_direction.norm() * intersection.norm).max(0.0);
+    /// Returns the light leaving a surface found by a diffuse or rough bounce, which picked ray
+    /// with probability density pdf.
+    ///
+    /// Glowing surfaces which are sampled as lights were also sampled at the bounce, so their
+    /// light is weighed against that.
+    fn bounce_color(&self, intersection: &Intersection, ray: &Ray, depth: i32, pdf: f64)
+                    -> Color {
+        let emission = match intersection.light {
+            Some(ref light) => {
+                let light_pdf = light.pdf(ray.origin, intersection) * light.samples() as f64;
+                intersection.material.emission * power_heuristic(pdf, light_pdf)
+            },
+            None => intersection.material.emission
+        };
 
-        shadow_color * material.matte_color * material.matte_intensity * cos
+        (emission + self.shade(intersection, ray, depth)) * self.transmittance(intersection, ray)
     }
 }
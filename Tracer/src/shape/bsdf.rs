@@ -0,0 +1,126 @@
+use geom::Color;
+use geom::Vec3;
+use geom::random::jitter;
+
+/// BsdfSample is a direction picked by Bsdf::sample, along with how light coming from it is
+/// scattered.
+pub struct BsdfSample {
+    /// The sampled (normalized) direction that light arrives from.
+    pub direction: Vec3,
+    /// The BSDF times the cosine of direction with the normal, divided by the pdf. This is what
+    /// the light arriving from direction is multiplied by.
+    pub weight: Color,
+    /// The probability density (per solid angle) of having picked direction. This is
+    /// meaningless for specular samples.
+    pub pdf: f64,
+    /// Whether direction came from a perfectly specular lobe (a mirror reflection or a
+    /// refraction), which only scatters light in that one direction.
+    pub specular: bool
+}
+
+impl BsdfSample {
+    /// Builds the sample for a direction wi which bsdf picked from one of its non-specular
+    /// lobes, weighing it by bsdf's full eval and pdf. Returns None if wi is below the surface
+    /// or couldn't have been picked.
+    pub fn scattered(bsdf: &Bsdf, wo: Vec3, wi: Vec3, norm: Vec3) -> Option<BsdfSample> {
+        let cos = (wi * norm).abs();
+        let pdf = bsdf.pdf(wo, wi, norm);
+        if cos == 0.0 || pdf <= 0.0 {
+            return None;
+        }
+
+        Some(BsdfSample {direction: wi, weight: bsdf.eval(wo, wi, norm) * (cos / pdf), pdf: pdf,
+                         specular: false})
+    }
+}
+
+/// A Bsdf (bidirectional scattering distribution function) describes how a surface scatters
+/// light: how much of the light arriving from one direction leaves towards another.
+///
+/// In every method, wo is the (normalized) direction that light leaves towards, wi the one it
+/// arrives from, and norm the surface's normal. All of them point away from the surface, and
+/// wo may be on either side of it.
+pub trait Bsdf: Send + Sync {
+    /// Evaluates the BSDF for light arriving from wi and leaving towards wo. Perfectly
+    /// specular lobes are left out, since they are zero in every direction they aren't sampled
+    /// in.
+    fn eval(&self, wo: Vec3, wi: Vec3, norm: Vec3) -> Color;
+
+    /// Returns the probability density (per solid angle) of sample picking wi when light leaves
+    /// towards wo. Like eval, this leaves out perfectly specular lobes.
+    fn pdf(&self, wo: Vec3, wi: Vec3, norm: Vec3) -> f64;
+
+    /// Picks a direction for light to arrive from, for light leaving the surface towards wo.
+    /// Returns None if no light is scattered towards wo (or the sample was wasted).
+    fn sample(&self, wo: Vec3, norm: Vec3) -> Option<BsdfSample>;
+
+    /// Returns roughly how much of the light arriving at the surface is scattered, which is
+    /// used to choose between the parts of a Combined BSDF.
+    fn albedo(&self) -> f64 {
+        1.0
+    }
+}
+
+/// Returns norm, flipped if needed to be on the same side of the surface as wo.
+pub fn facing(wo: Vec3, norm: Vec3) -> Vec3 {
+    if wo * norm >= 0.0 { norm } else { -norm }
+}
+
+/// Combined is the sum of several BSDFs, like a matte base with a glossy coat. When sampling,
+/// one of them is picked in proportion to its albedo.
+pub struct Combined {
+    bsdfs: Vec<Box<Bsdf>>,
+    /// The chance of sampling each of the bsdfs.
+    chances: Vec<f64>
+}
+
+impl Combined {
+    pub fn new(bsdfs: Vec<Box<Bsdf>>) -> Combined {
+        let total: f64 = bsdfs.iter().map(|bsdf| bsdf.albedo()).sum();
+        let chances = bsdfs.iter()
+                           .map(|bsdf| if total > 0.0 { bsdf.albedo() / total } else { 0.0 })
+                           .collect();
+
+        Combined {bsdfs: bsdfs, chances: chances}
+    }
+}
+
+impl Bsdf for Combined {
+    fn eval(&self, wo: Vec3, wi: Vec3, norm: Vec3) -> Color {
+        self.bsdfs.iter().fold(Color::black(), |f, bsdf| f + bsdf.eval(wo, wi, norm))
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3, norm: Vec3) -> f64 {
+        self.bsdfs.iter()
+                  .zip(self.chances.iter())
+                  .map(|(bsdf, chance)| chance * bsdf.pdf(wo, wi, norm))
+                  .sum()
+    }
+
+    fn sample(&self, wo: Vec3, norm: Vec3) -> Option<BsdfSample> {
+        let mut choice = jitter();
+        let index = self.chances.iter().position(|&chance| {
+            choice -= chance;
+            choice < 0.0
+        });
+
+        let (bsdf, chance) = match index {
+            Some(index) => (&self.bsdfs[index], self.chances[index]),
+            None => return None
+        };
+
+        let sample = bsdf.sample(wo, norm)?;
+        if sample.specular {
+            return Some(BsdfSample {direction: sample.direction,
+                                    weight: sample.weight * (1.0 / chance),
+                                    pdf: sample.pdf * chance, specular: true});
+        }
+
+        // Every other part could have picked the direction too.
+        BsdfSample::scattered(self, wo, sample.direction, norm)
+    }
+
+    fn albedo(&self) -> f64 {
+        self.bsdfs.iter().map(|bsdf| bsdf.albedo()).sum()
+    }
+}
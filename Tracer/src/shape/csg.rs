@@ -0,0 +1,163 @@
+use ::Arc;
+use geom::Ray;
+use geom::AABB;
+use shape::Shape;
+use shape::Intersection;
+use shape::Material;
+
+/// The boolean operations a Csg shape can combine its two children with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CsgOperation {
+    /// Everything inside either child.
+    Union,
+    /// Everything inside both children.
+    Intersection,
+    /// Everything inside the left child but not inside the right child.
+    Difference
+}
+
+impl CsgOperation {
+    /// Returns whether a point is inside the combined shape, given whether it is inside each of
+    /// the children.
+    fn contains(&self, in_left: bool, in_right: bool) -> bool {
+        match *self {
+            CsgOperation::Union => in_left || in_right,
+            CsgOperation::Intersection => in_left && in_right,
+            CsgOperation::Difference => in_left && !in_right
+        }
+    }
+}
+
+/// Csg combines two solid Shapes with a boolean operation (constructive solid geometry).
+///
+/// Both children must be closed solids: every Ray which enters one must leave it again, and
+/// intersect_all must report both crossings with normals pointing out of the solid. A Ray is
+/// taken to start inside a child if the first crossing it reports is an exit.
+///
+/// The children's hit lists are merged in order of distance, keeping track of whether the Ray is
+/// inside each child. Only the crossings where the Ray enters or leaves the combined shape are
+/// kept.
+pub struct Csg {
+    operation: CsgOperation,
+    left: Arc<Shape>,
+    right: Arc<Shape>,
+    material: Option<Arc<Material>>
+}
+
+impl Csg {
+    /// Constructs a Csg shape from two children. Each surface keeps the material of the child it
+    /// came from, until set_material is called.
+    pub fn new(operation: CsgOperation, left: Arc<Shape>, right: Arc<Shape>) -> Csg {
+        Csg {operation: operation, left: left, right: right, material: None}
+    }
+
+    /// Constructs the union of two Shapes.
+    pub fn union(left: Arc<Shape>, right: Arc<Shape>) -> Csg {
+        Csg::new(CsgOperation::Union, left, right)
+    }
+
+    /// Constructs the intersection of two Shapes.
+    pub fn intersection(left: Arc<Shape>, right: Arc<Shape>) -> Csg {
+        Csg::new(CsgOperation::Intersection, left, right)
+    }
+
+    /// Constructs the left Shape with the right Shape cut out of it.
+    pub fn difference(left: Arc<Shape>, right: Arc<Shape>) -> Csg {
+        Csg::new(CsgOperation::Difference, left, right)
+    }
+}
+
+/// Returns a child's intersections sorted by distance, and whether the Ray starts inside it.
+fn sorted_hits(shape: &Arc<Shape>, ray: Ray) -> (Vec<Intersection>, bool) {
+    let mut hits = shape.intersect_all(ray);
+    hits.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
+
+    let inside = hits.first().map_or(false, |hit| hit.norm * ray.direction > 0.0);
+
+    (hits, inside)
+}
+
+impl Shape for Csg {
+    /// Returns the first, closest intersection of a Ray and the combined shape.
+    fn intersect_first(&self, ray: Ray) -> Option<Intersection> {
+        self.intersect_all(ray).into_iter().next()
+    }
+
+    /// Returns every point where the Ray enters or leaves the combined shape, sorted by distance.
+    fn intersect_all(&self, ray: Ray) -> Vec<Intersection> {
+        let (left_hits, mut in_left) = sorted_hits(&self.left, ray);
+        let (right_hits, mut in_right) = sorted_hits(&self.right, ray);
+
+        let mut left_iter = left_hits.into_iter().peekable();
+        let mut right_iter = right_hits.into_iter().peekable();
+        let mut ret = Vec::new();
+
+        loop {
+            let from_left = match (left_iter.peek(), right_iter.peek()) {
+                (Some(l), Some(r)) => l.distance <= r.distance,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => break
+            };
+
+            let was_inside = self.operation.contains(in_left, in_right);
+
+            let mut hit = if from_left {
+                in_left = !in_left;
+                left_iter.next().unwrap()
+            } else {
+                in_right = !in_right;
+                right_iter.next().unwrap()
+            };
+
+            if self.operation.contains(in_left, in_right) == was_inside {
+                continue;
+            }
+
+            // The surface of a subtracted shape faces into the result, so it has to be flipped.
+            if !from_left && self.operation == CsgOperation::Difference {
+                hit.norm = -hit.norm;
+            }
+
+            if let Some(ref material) = self.material {
+                hit.material = material.clone();
+            }
+
+            ret.push(hit);
+        }
+
+        ret
+    }
+
+    /// Returns a box enclosing the combined shape, or None if it is unbounded.
+    fn bounding_box(&self) -> Option<AABB> {
+        let left = self.left.bounding_box();
+        let right = self.right.bounding_box();
+
+        match self.operation {
+            CsgOperation::Union => match (left, right) {
+                (Some(l), Some(r)) => Some(l.union(&r)),
+                _ => None
+            },
+            CsgOperation::Intersection => match (left, right) {
+                (Some(l), Some(r)) => Some(l.overlap(&r)),
+                (Some(l), None) => Some(l),
+                (None, r) => r
+            },
+            CsgOperation::Difference => left
+        }
+    }
+
+    /// Sets the material of every surface of the combined shape.
+    fn set_material(&mut self, material: Arc<Material>) {
+        self.material = Some(material);
+    }
+
+    /// Gets the material of the combined shape, which defaults to that of the left child.
+    fn get_material(&self) -> Arc<Material> {
+        match self.material {
+            Some(ref material) => material.clone(),
+            None => self.left.get_material()
+        }
+    }
+}
@@ -1,30 +1,47 @@
 use ::Arc;
+use std::f64::consts::PI;
 use geom::Vec3;
+use geom::random;
+use geom::random::jitter;
 use geom::Ray;
+use geom::AABB;
 use shape::Shape;
+use shape::SurfaceSample;
 use shape::Intersection;
 use shape::Material;
 
+/// Cylinder is a solid, closed cylinder: a tube with a flat, circular cap on each end.
+///
+/// An open tube (with no caps) can be constructed with Cylinder::open.
 pub struct Cylinder {
     start_cap: Vec3,
     axis: Vec3,
     radius: f64,
     height: f64,
+    capped: bool,
     material: Arc<Material>
 }
 
 impl Cylinder {
+    /// Constructs a closed Cylinder which starts at start_cap and extends height along axis.
     pub fn new(start_cap: Vec3, axis: Vec3, radius: f64, height: f64, material: Arc<Material>) ->
            Cylinder {
         Cylinder {start_cap: start_cap, axis: axis.norm(), radius: radius,
-                  height: height, material: material}
+                  height: height, capped: true, material: material}
     }
-}
 
-impl Shape for Cylinder {
-    /// Returns the first, closest intersection of a Ray and the Shape, or None if there are no
-    /// intersections.
-    fn intersect_first(&self, ray: Ray) -> Option<Intersection> {
+    /// Constructs an open tube which starts at start_cap and extends height along axis.
+    pub fn open(start_cap: Vec3, axis: Vec3, radius: f64, height: f64, material: Arc<Material>)
+                -> Cylinder {
+        Cylinder {start_cap: start_cap, axis: axis.norm(), radius: radius,
+                  height: height, capped: false, material: material}
+    }
+
+    /// Returns every intersection of the Ray with the tube and (if the Cylinder is capped) the
+    /// caps, sorted by distance.
+    fn intersections(&self, ray: Ray) -> Vec<Intersection> {
+        let mut vec = Vec::new();
+
         let D = ray.direction;
         let V = self.axis;
         let X = ray.origin - self.start_cap;
@@ -40,9 +57,8 @@ impl Shape for Cylinder {
 
             if m > 0.0 && m < self.height {
                 let point = ray.origin + D * t1;
-                let norm_point = point - self.start_cap;
-                let norm = (norm_point - norm_point.project(V)).norm();
-                return Some(Intersection::new(t1, self.material.clone(), point, norm));
+                let norm = (point - self.start_cap - V * m).norm();
+                vec.push(Intersection::new(t1, self.material.clone(), point, norm));
             }
         }
 
@@ -52,58 +68,63 @@ impl Shape for Cylinder {
 
             if m > 0.0 && m < self.height {
                 let point = ray.origin + D * t2;
-                // we define a factor to flip the normal if we are looking in the cylinder from
-                // within the cap hole. Otherwise, the cylinder is completely black inside.
-                let flip = if t1 > 0.0 { -1.0 } else { 1.0 };
+                // For an open tube, we define a factor to flip the normal if we are looking in
+                // the cylinder from within the cap hole. Otherwise, the cylinder is completely
+                // black inside. A closed Cylinder's normals always point out of the solid.
+                let flip = if !self.capped && t1 > 0.0 { -1.0 } else { 1.0 };
                 let norm = (point - self.start_cap - V * m).norm() * flip;
-                return Some(Intersection::new(t2, self.material.clone(), point, norm));
+                vec.push(Intersection::new(t2, self.material.clone(), point, norm));
             }
         }
 
-        None // :(
-    }
-
-    /// Returns a Vec containing all possible intersections of a Ray and a Shape.
-    fn intersect_all(&self, ray: Ray) -> Vec<Intersection> {
-        let mut vec = Vec::new();
-
-        let D = ray.direction;
-        let V = self.axis;
-        let X = ray.origin - self.start_cap;
+        if self.capped && D * V != 0.0 {
+            // Each cap is a disk in the plane through its center perpendicular to the axis.
+            let caps = [(self.start_cap, -V), (self.start_cap + V * self.height, V)];
 
-        // Cylinder intersection points are solutions to at^2+bt+c=0.
-        let a = 1.0 - (D * V).powi(2);
-        let b = 2.0 * (D * X - (D * V) * (X * V));
-        let c = X * X - (X * V).powi(2) - self.radius.powi(2);
+            for &(center, norm) in caps.iter() {
+                let t = ((center - ray.origin) * V) / (D * V);
 
-        let t1 = (-b - (b * b - 4.0 * a * c).sqrt()) / (2.0 * a);
-        if t1 > 0.0 {
-            let m = (D * V) * t1 + X * V;
+                if t > 0.0 {
+                    let point = ray.origin + D * t;
 
-            if m > 0.0 && m < self.height {
-                let point = ray.origin + D * t1;
-                let norm_point = point - self.start_cap;
-                let norm = (norm_point - norm_point.project(V)).norm();
-                vec.push(Intersection::new(t1, self.material.clone(), point, norm));
+                    if (point - center).mag() <= self.radius {
+                        vec.push(Intersection::new(t, self.material.clone(), point, norm));
+                    }
+                }
             }
-        }
-
-        let t2 = (-b + (b * b - 4.0 * a * c).sqrt()) / (2.0 * a);
-        if t2 > 0.0 {
-            let m = (D * V) * t2 + X * V;
 
-            if m > 0.0 && m < self.height {
-                let point = ray.origin + D * t2;
-                // we define a factor to flip the normal if we are looking in the cylinder from
-                // within the cap hole. Otherwise, the cylinder is completely black inside.
-                let flip = if t1 > 0.0 { -1.0 } else { 1.0 };
-                let norm = (point - self.start_cap - V * m).norm() * flip;
-                vec.push(Intersection::new(t2, self.material.clone(), point, norm));
-            }
+            vec.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
         }
 
         vec
     }
+}
+
+impl Shape for Cylinder {
+    /// Returns the first, closest intersection of a Ray and the Shape, or None if there are no
+    /// intersections.
+    fn intersect_first(&self, ray: Ray) -> Option<Intersection> {
+        self.intersections(ray).into_iter().next()
+    }
+
+    /// Returns a Vec containing all possible intersections of a Ray and a Shape.
+    fn intersect_all(&self, ray: Ray) -> Vec<Intersection> {
+        self.intersections(ray)
+    }
+
+    /// Returns the box enclosing both circular ends of the Cylinder.
+    fn bounding_box(&self) -> Option<AABB> {
+        // A circle of radius r perpendicular to the unit axis V reaches r * sqrt(1 - V_i^2) away
+        // from its center along each coordinate axis i.
+        let V = self.axis;
+        let extent = Vec3::new(self.radius * (1.0 - V.x * V.x).max(0.0).sqrt(),
+                               self.radius * (1.0 - V.y * V.y).max(0.0).sqrt(),
+                               self.radius * (1.0 - V.z * V.z).max(0.0).sqrt());
+        let end_cap = self.start_cap + V * self.height;
+
+        Some(AABB::from_points(&[self.start_cap - extent, self.start_cap + extent,
+                                 end_cap - extent, end_cap + extent]))
+    }
 
     /// Sets the material of the Shape.
     fn set_material(&mut self, material: Arc<Material>) {
@@ -114,4 +135,30 @@ impl Shape for Cylinder {
     fn get_material(&self) -> Arc<Material> {
         self.material.clone()
     }
+
+    /// Picks a point evenly over the tube and (if the Cylinder is capped) the caps.
+    fn sample_surface(&self) -> Option<SurfaceSample> {
+        let tube_area = 2.0 * PI * self.radius * self.height;
+        let cap_area = if self.capped { PI * self.radius * self.radius } else { 0.0 };
+        let total = tube_area + 2.0 * cap_area;
+        let (u, v) = self.axis.basis();
+
+        // Pick the tube or a cap in proportion to their areas.
+        let pick = jitter() * total;
+        let (position, norm) = if pick < tube_area {
+            let phi = 2.0 * PI * jitter();
+            let norm = u * phi.cos() + v * phi.sin();
+            (self.start_cap + self.axis * (jitter() * self.height) + norm * self.radius, norm)
+        } else {
+            let (x, y) = random::disk();
+            let offset = (u * x + v * y) * self.radius;
+            if pick < tube_area + cap_area {
+                (self.start_cap + offset, -self.axis)
+            } else {
+                (self.start_cap + self.axis * self.height + offset, self.axis)
+            }
+        };
+
+        Some(SurfaceSample {position: position, norm: norm, pdf: 1.0 / total})
+    }
 }
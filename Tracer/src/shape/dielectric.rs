@@ -0,0 +1,72 @@
+use geom::Color;
+use geom::Vec3;
+use geom::random::jitter;
+use shape::bsdf::{Bsdf, BsdfSample, facing};
+
+/// Dielectric is the smooth surface of a clear material like glass or water. Light is split
+/// between reflection and refraction by the Fresnel equations, and is totally internally
+/// reflected when it can't leave the inside of the material.
+///
+/// Only refracted light is tinted. Light can also be absorbed while it travels through the
+/// inside of a material, which is set on the Material.
+pub struct Dielectric {
+    color: Color,
+    eta: f64
+}
+
+impl Dielectric {
+    /// Constructs a dielectric with index of refraction eta, which tints refracted light by
+    /// color.
+    pub fn new(color: Color, eta: f64) -> Dielectric {
+        assert!(eta > 0.0, "Cannot have a dielectric with a non-positive index of refraction!");
+        Dielectric {color: color, eta: eta}
+    }
+}
+
+impl Bsdf for Dielectric {
+    fn eval(&self, _: Vec3, _: Vec3, _: Vec3) -> Color {
+        Color::black()
+    }
+
+    fn pdf(&self, _: Vec3, _: Vec3, _: Vec3) -> f64 {
+        0.0
+    }
+
+    /// Picks reflection or refraction in proportion to the Fresnel reflectance.
+    fn sample(&self, wo: Vec3, norm: Vec3) -> Option<BsdfSample> {
+        let entering = wo * norm > 0.0;
+        let norm = facing(wo, norm);
+        let eta = if entering { 1.0 / self.eta } else { self.eta };
+        let reflectance = fresnel(wo * norm, eta);
+
+        let (wi, color, chance) = match wo.refract(norm, eta) {
+            Some(wi) if jitter() >= reflectance => (wi, self.color, 1.0 - reflectance),
+            _ => (wo.reflect(norm), Color::white(), reflectance)
+        };
+
+        Some(BsdfSample {direction: wi, weight: color, pdf: chance, specular: true})
+    }
+
+    fn albedo(&self) -> f64 {
+        self.color.luminance()
+    }
+}
+
+/// Returns the fraction of light reflected by the smooth surface between two dielectrics, for
+/// light arriving at an angle with cosine cos_i from the normal. eta is the index of refraction
+/// on the light's side over the index on the other side, like in Vec3::refract. This is 1 when
+/// the light is totally internally reflected.
+pub fn fresnel(cos_i: f64, eta: f64) -> f64 {
+    let cos_i = cos_i.abs().min(1.0);
+    let sin2_t = eta * eta * (1.0 - cos_i * cos_i);
+
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let perpendicular = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let parallel = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+
+    (perpendicular * perpendicular + parallel * parallel) / 2.0
+}
@@ -1,7 +1,7 @@
+use std::f64::INFINITY;
 use geom::*;
-use shape::Intersection;
 use shape::Light;
-use scene::World;
+use shape::LightSample;
 
 pub struct DirectionLight {
     direction: Vec3,
@@ -15,14 +15,9 @@ impl DirectionLight {
 }
 
 impl Light for DirectionLight {
-    fn in_shadow(&self, world: &World, intersection: &Intersection) -> Option<Vec3> {
-        let shadow_ray = Ray::new(intersection.position, -(self.direction)).step_epsilon();
-
-        if let Some(_) = world.get_closest_intersection(&shadow_ray) {
-            return None;
-        }
-
-        Some(-(self.direction))
+    fn sample(&self, _position: Vec3) -> Option<LightSample> {
+        Some(LightSample {direction: -(self.direction), distance: INFINITY, radiance: self.color,
+                          pdf: 1.0})
     }
 
     fn get_color(&self) -> Color {
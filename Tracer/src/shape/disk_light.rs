@@ -0,0 +1,68 @@
+use std::f64::consts::PI;
+use geom::Vec3;
+use geom::Color;
+use geom::random;
+use shape::Light;
+use shape::LightSample;
+
+/// The default number of shadow rays traced towards an area light.
+const SAMPLES: u32 = 16;
+
+/// DiskLight is a round light which glows evenly over its surface, casting soft shadows.
+///
+/// It only shines from its front side, which is the side its normal points towards.
+pub struct DiskLight {
+    center: Vec3,
+    normal: Vec3,
+    radius: f64,
+    color: Color,
+    samples: u32
+}
+
+impl DiskLight {
+    pub fn new(center: Vec3, normal: Vec3, radius: f64, color: Color) -> DiskLight {
+        DiskLight {center: center, normal: normal.norm(), radius: radius, color: color,
+                   samples: SAMPLES}
+    }
+
+    /// Sets the number of shadow rays traced towards the light from each lit point. More rays
+    /// give smoother shadows.
+    pub fn set_samples(&mut self, samples: u32) {
+        assert!(samples > 0, "Cannot sample a light with no rays!");
+        self.samples = samples;
+    }
+}
+
+impl Light for DiskLight {
+    fn sample(&self, position: Vec3) -> Option<LightSample> {
+        let (a, b) = self.normal.basis();
+        let (x, y) = random::disk();
+        let point = self.center + a * (x * self.radius) + b * (y * self.radius);
+
+        let L = point - position;
+        let distance = L.mag();
+        let direction = L * (1.0 / distance);
+        let cos = -(direction * self.normal);
+
+        if cos <= 0.0 {
+            return None;
+        }
+
+        // Points are picked evenly over the area, so turn the pdf into one over solid angle.
+        let area = PI * self.radius * self.radius;
+        Some(LightSample {direction: direction, distance: distance, radiance: self.color,
+                          pdf: distance * distance / (area * cos)})
+    }
+
+    fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    fn get_color(&self) -> Color {
+        self.color
+    }
+
+    fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+}
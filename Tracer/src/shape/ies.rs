@@ -0,0 +1,186 @@
+use std::io;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::fs::File;
+use geom::Vec3;
+
+/// IesProfile is the measured intensity of a real light fixture in every direction, read from
+/// an IESNA LM-63 photometric data (.ies) file.
+///
+/// Intensities are scaled so that the brightest direction is 1, so the profile only shapes a
+/// light and the light's color still sets how bright it is. Only type C photometry (the kind
+/// used for nearly all architectural fixtures) is supported: vertical angles are measured from
+/// the fixture's axis, which points straight down out of the fixture, and horizontal angles go
+/// around that axis.
+pub struct IesProfile {
+    vertical_angles: Vec<f64>,
+    horizontal_angles: Vec<f64>,
+    /// The relative intensities, one row of vertical angles per horizontal angle.
+    intensities: Vec<Vec<f64>>
+}
+
+impl IesProfile {
+    /// Loads a profile from the .ies file at filename.
+    pub fn load(filename: &str) -> io::Result<IesProfile> {
+        let file = File::open(filename)?;
+        IesProfile::read_ies(BufReader::new(file))
+    }
+
+    /// Reads a profile in the LM-63 format from any buffered reader.
+    pub fn read_ies<R: BufRead>(reader: R) -> io::Result<IesProfile> {
+        let mut lines = reader.lines();
+
+        // The header is free-form keywords, up to the line which says how lamp tilt is given.
+        let tilt = loop {
+            match lines.next() {
+                Some(line) => {
+                    let line = line?;
+                    if line.trim_start().starts_with("TILT=") {
+                        break line.trim()["TILT=".len()..].to_string();
+                    }
+                },
+                None => return Err(ies_error("missing TILT line"))
+            }
+        };
+
+        // Everything after the TILT line is numbers, separated by whitespace or commas.
+        let mut rest = String::new();
+        for line in lines {
+            rest.push_str(&line?);
+            rest.push('\n');
+        }
+        let mut numbers = rest.split(|c: char| c.is_whitespace() || c == ',')
+                              .filter(|token| !token.is_empty())
+                              .map(|token| token.parse::<f64>()
+                                                .map_err(|_| ies_error("expected a number")));
+        let mut next = || numbers.next().unwrap_or(Err(ies_error("unexpected end of file")));
+
+        // Tilt data changes the output with the lamp's tilt, which we don't model, so it is
+        // read past and ignored.
+        if tilt == "INCLUDE" {
+            next()?;
+            let pairs = next()? as usize;
+            for _ in 0..2 * pairs {
+                next()?;
+            }
+        }
+
+        let _lamps = next()?;
+        let _lumens = next()?;
+        let multiplier = next()?;
+        let num_vertical = next()?;
+        let num_horizontal = next()?;
+        let photometric_type = next()?;
+        for _ in 0..7 {
+            // Units, luminous opening size, ballast factor and input watts.
+            next()?;
+        }
+
+        if photometric_type != 1.0 {
+            return Err(ies_error("only type C photometry is supported"));
+        }
+        if num_vertical < 1.0 || num_horizontal < 1.0 {
+            return Err(ies_error("a profile needs at least one angle of each kind"));
+        }
+
+        let mut read_list = |count: usize| {
+            (0..count).map(|_| next()).collect::<io::Result<Vec<f64>>>()
+        };
+        let vertical_angles = read_list(num_vertical as usize)?;
+        let horizontal_angles = read_list(num_horizontal as usize)?;
+        let mut intensities = Vec::new();
+        for _ in 0..horizontal_angles.len() {
+            intensities.push(read_list(vertical_angles.len())?);
+        }
+
+        let is_sorted = |angles: &[f64]| angles.windows(2).all(|pair| pair[0] < pair[1]);
+        if !is_sorted(&vertical_angles) || !is_sorted(&horizontal_angles) {
+            return Err(ies_error("angles must be increasing"));
+        }
+
+        let peak = intensities.iter()
+                              .flat_map(|row| row.iter())
+                              .fold(0.0f64, |peak, &candela| peak.max(candela * multiplier));
+        if peak <= 0.0 {
+            return Err(ies_error("the fixture gives off no light"));
+        }
+
+        for row in intensities.iter_mut() {
+            for candela in row.iter_mut() {
+                *candela = (*candela * multiplier / peak).max(0.0);
+            }
+        }
+
+        Ok(IesProfile {vertical_angles: vertical_angles, horizontal_angles: horizontal_angles,
+                       intensities: intensities})
+    }
+
+    /// Returns the relative intensity given off in direction by a fixture whose axis points
+    /// along axis. Horizontal angles are measured around the axis from the first vector of
+    /// axis.basis().
+    pub fn intensity_along(&self, axis: Vec3, direction: Vec3) -> f64 {
+        let (u, v) = axis.basis();
+        let theta = (direction * axis).max(-1.0).min(1.0).acos();
+        let phi = (direction * v).atan2(direction * u);
+
+        self.intensity(theta, phi)
+    }
+
+    /// Returns the relative intensity (on [0, 1]) given off at vertical angle theta from the
+    /// fixture's axis and horizontal angle phi around it, both in radians.
+    pub fn intensity(&self, theta: f64, phi: f64) -> f64 {
+        let theta = theta.to_degrees();
+        let mut phi = phi.to_degrees() % 360.0;
+        if phi < 0.0 {
+            phi += 360.0;
+        }
+
+        // Profiles with symmetry only list some of the horizontal angles, so fold phi onto them.
+        let last = *self.horizontal_angles.last().unwrap();
+        if last == 0.0 {
+            phi = 0.0;
+        } else if last <= 90.0 {
+            if phi > 180.0 { phi = 360.0 - phi; }
+            if phi > 90.0 { phi = 180.0 - phi; }
+        } else if last <= 180.0 {
+            if phi > 180.0 { phi = 360.0 - phi; }
+        }
+
+        let (v, v_fraction) = match locate(&self.vertical_angles, theta) {
+            Some(location) => location,
+            None => return 0.0
+        };
+        let (h, h_fraction) = locate(&self.horizontal_angles, phi).unwrap_or((0, 0.0));
+
+        let row = |h: usize| {
+            let row = &self.intensities[h];
+            let next = row[(v + 1).min(row.len() - 1)];
+            row[v] * (1.0 - v_fraction) + next * v_fraction
+        };
+        let next_h = (h + 1).min(self.horizontal_angles.len() - 1);
+
+        row(h) * (1.0 - h_fraction) + row(next_h) * h_fraction
+    }
+}
+
+/// Finds the index of the angle just below angle in a sorted list and how far angle is towards
+/// the next one. Returns None if angle is outside of the list.
+fn locate(angles: &[f64], angle: f64) -> Option<(usize, f64)> {
+    let first = angles[0];
+    let last = angles[angles.len() - 1];
+
+    if angle < first || angle > last {
+        return None;
+    }
+    if angles.len() == 1 || angle == last {
+        return Some((angles.len() - 1, 0.0));
+    }
+
+    let i = angles.windows(2).position(|pair| angle < pair[1]).unwrap_or(angles.len() - 2);
+    Some((i, (angle - angles[i]) / (angles[i + 1] - angles[i])))
+}
+
+/// Builds the io::Error returned for malformed .ies files.
+fn ies_error(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
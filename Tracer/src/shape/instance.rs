@@ -0,0 +1,82 @@
+use ::Arc;
+use geom::Ray;
+use geom::AABB;
+use geom::Transform;
+use shape::Shape;
+use shape::Intersection;
+use shape::Material;
+
+/// Instance places a shared Shape in the World under a Transform.
+///
+/// The inner Shape is defined in its own "object space". Rays are moved into object space, the
+/// inner Shape is intersected there, and the hit positions and normals are moved back out. Since
+/// the inner Shape is only referenced, one (possibly large) TriangleMesh can be placed in a World
+/// many times without being copied.
+pub struct Instance {
+    shape: Arc<Shape>,
+    transform: Transform,
+    material: Option<Arc<Material>>
+}
+
+impl Instance {
+    /// Constructs an Instance of shape placed by transform. It uses the inner Shape's material
+    /// until set_material is called.
+    pub fn new(shape: Arc<Shape>, transform: Transform) -> Instance {
+        Instance {shape: shape, transform: transform, material: None}
+    }
+
+    /// Gets the Transform from the inner Shape's space to world space.
+    pub fn get_transform(&self) -> Transform {
+        self.transform
+    }
+
+    /// Moves an intersection found in object space back out into world space.
+    fn to_world(&self, ray: &Ray, local: Intersection) -> Intersection {
+        let position = self.transform.point(local.position);
+        let norm = self.transform.normal(local.norm);
+        // Distances along the object space Ray are scaled by the Transform, so measure again.
+        let distance = (position - ray.origin).mag();
+        let material = match self.material {
+            Some(ref material) => material.clone(),
+            None => local.material
+        };
+
+        Intersection::new(distance, material, position, norm)
+    }
+}
+
+impl Shape for Instance {
+    /// Returns the first, closest intersection of a Ray and the transformed Shape.
+    fn intersect_first(&self, ray: Ray) -> Option<Intersection> {
+        let local_ray = self.transform.inverse_ray(&ray);
+
+        self.shape.intersect_first(local_ray).map(|local| self.to_world(&ray, local))
+    }
+
+    /// Returns a Vec containing all intersections of a Ray and the transformed Shape.
+    fn intersect_all(&self, ray: Ray) -> Vec<Intersection> {
+        let local_ray = self.transform.inverse_ray(&ray);
+
+        self.shape.intersect_all(local_ray).into_iter()
+                                           .map(|local| self.to_world(&ray, local))
+                                           .collect()
+    }
+
+    /// Returns the box enclosing the transformed bounding box of the inner Shape.
+    fn bounding_box(&self) -> Option<AABB> {
+        self.shape.bounding_box().map(|aabb| self.transform.aabb(&aabb))
+    }
+
+    /// Sets the material of this Instance, leaving the shared inner Shape untouched.
+    fn set_material(&mut self, material: Arc<Material>) {
+        self.material = Some(material);
+    }
+
+    /// Gets the material of the Instance.
+    fn get_material(&self) -> Arc<Material> {
+        match self.material {
+            Some(ref material) => material.clone(),
+            None => self.shape.get_material()
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use ::Arc;
 use geom::Vec3;
 use shape::Material;
+use shape::ShapeLight;
 
 /// The Intersection struct represents an intersection of a Ray and a Shape object for ray tracing.
 #[derive(Clone)]
@@ -8,11 +9,15 @@ pub struct Intersection {
     pub distance: f64,
     pub material: Arc<Material>,
     pub position: Vec3,
-    pub norm: Vec3
+    pub norm: Vec3,
+    /// The light which samples the shape that was hit, if it is a glowing shape which was added
+    /// to the World as a light. This is only filled in by the World.
+    pub light: Option<Arc<ShapeLight>>
 }
 
 impl Intersection {
     pub fn new(distance: f64, material: Arc<Material>, position: Vec3, norm: Vec3) -> Intersection {
-        Intersection {distance: distance, material: material, position: position, norm: norm}
+        Intersection {distance: distance, material: material, position: position, norm: norm,
+                      light: None}
     }
 }
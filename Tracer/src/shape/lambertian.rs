@@ -0,0 +1,48 @@
+use std::f64::consts::PI;
+use geom::Color;
+use geom::Vec3;
+use shape::bsdf::{Bsdf, BsdfSample, facing};
+
+/// Lambertian is a perfectly matte surface, which scatters light evenly in every direction.
+pub struct Lambertian {
+    color: Color
+}
+
+impl Lambertian {
+    /// Constructs a matte surface which reflects color (its albedo).
+    pub fn new(color: Color) -> Lambertian {
+        Lambertian {color: color}
+    }
+}
+
+impl Bsdf for Lambertian {
+    fn eval(&self, wo: Vec3, wi: Vec3, norm: Vec3) -> Color {
+        // Matte surfaces only reflect, so wo and wi must be on the same side of the surface.
+        if (wo * norm) * (wi * norm) <= 0.0 {
+            return Color::black();
+        }
+
+        self.color * (1.0 / PI)
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3, norm: Vec3) -> f64 {
+        if (wo * norm) * (wi * norm) <= 0.0 {
+            return 0.0;
+        }
+
+        (wi * norm).abs() / PI
+    }
+
+    /// Picks directions by the cosine of their angle with the normal.
+    fn sample(&self, wo: Vec3, norm: Vec3) -> Option<BsdfSample> {
+        let norm = facing(wo, norm);
+        let (u, v) = norm.basis();
+        let local = Vec3::sample_hemisphere(1.0);
+
+        BsdfSample::scattered(self, wo, u * local.x + norm * local.y + v * local.z, norm)
+    }
+
+    fn albedo(&self) -> f64 {
+        self.color.luminance()
+    }
+}
@@ -1,10 +1,67 @@
 use geom::Vec3;
 use geom::Color;
+use geom::Ray;
 use shape::Intersection;
 use scene::World;
 
+/// LightSample is a point on a Light which was picked to light a point in the scene.
+#[derive(Debug, Clone, Copy)]
+pub struct LightSample {
+    /// The normalized direction from the lit point towards the light.
+    pub direction: Vec3,
+    /// How far the sampled point on the light is from the lit point. This is infinite for
+    /// lights which are infinitely far away.
+    pub distance: f64,
+    /// The light arriving at the lit point from the sample.
+    pub radiance: Color,
+    /// The probability density (per solid angle) of having picked the sample. Lights which
+    /// only shine from a single point or direction always pick it, with a pdf of 1.
+    pub pdf: f64
+}
+
+/// Light is the trait implemented by everything which shines light onto the scene.
+///
+/// Lights are sampled: each call to sample may pick a different point on the light, and the
+/// light reaching a point is estimated by averaging radiance / pdf over many samples. Lights
+/// which are a single point or direction give the same sample every time.
 pub trait Light : Send + Sync {
-    fn in_shadow(&self, world: &World, intersection: &Intersection) -> Option<Vec3>;
+    /// Picks a point on the light to light position with, without checking whether anything is
+    /// in the way. Returns None if no light from the light can reach the position, like when
+    /// it is behind a one-sided light.
+    fn sample(&self, position: Vec3) -> Option<LightSample>;
+
+    /// Samples the light as seen from intersection, returning the sample only if nothing in
+    /// world is in the way (so the intersection isn't in the light's shadow).
+    fn in_shadow(&self, world: &World, intersection: &Intersection) -> Option<LightSample> {
+        let sample = match self.sample(intersection.position) {
+            Some(sample) => sample,
+            None => return None
+        };
+
+        // We use step_epsilon() so the shadow ray doesn't intersect the object it is emitted
+        // from. Without it, we have a lot of black-dotted noise.
+        let shadow_ray = Ray::new(intersection.position, sample.direction).step_epsilon();
+
+        if world.is_occluded(&shadow_ray, sample.distance) {
+            return None;
+        }
+
+        Some(sample)
+    }
+
+    /// How many samples should be averaged to find the light reaching a point. Lights with no
+    /// area only need 1.
+    fn samples(&self) -> u32 {
+        1
+    }
+
+    /// Whether rays traced through the scene can hit the light itself, so that its light is
+    /// also found by sampling BSDFs and both ways of finding it have to be weighed against each
+    /// other. Only glowing shapes can be hit.
+    fn is_visible(&self) -> bool {
+        false
+    }
+
     fn get_color(&self) -> Color;
     fn set_color(&mut self, color: Color);
 }
@@ -1,41 +1,248 @@
+use ::Arc;
 use geom::Color;
+use geom::Vec3;
+use geom::random::jitter;
+use shape::bsdf::{Bsdf, BsdfSample, Combined, facing};
+use shape::dielectric::fresnel;
+use shape::{Dielectric, Lambertian, Microfacet, Mirror, Phong, Texture};
 
+/// Material is everything about how the surface of a Shape (and the inside of it) looks: how
+/// the surface scatters light, the light the surface gives off, and how much light is absorbed
+/// inside.
+///
+/// The surface is either a single BSDF, or made out of other materials: a blend of two of them,
+/// or a clear coat over another one.
 pub struct Material {
-    pub matte_intensity: f64,
-    pub matte_color: Color,
-    pub glossy_intensity: f64,
-    pub glossy_power: f64,
-    pub glossy_color: Color,
-    pub trans_intensity: f64,
-    pub trans_color: Color,
-    pub trans_eta: f64
+    surface: Surface,
+    /// How quickly each color of light is absorbed while travelling through the inside of the
+    /// material, per unit of distance.
+    pub absorption: Color,
+    /// The light given off by the surface itself, on both of its sides.
+    pub emission: Color
 }
 
 impl Material {
-    pub fn new(matte_intensity: f64, matte_color: Color, glossy_intensity: f64, glossy_power: f64,
-               glossy_color: Color,  trans_intensity: f64, trans_color: Color, trans_eta: f64)
-               -> Material {
-        Material {matte_intensity: matte_intensity, matte_color: matte_color,
-                  glossy_intensity: glossy_intensity, glossy_power: glossy_power,
-                  glossy_color: glossy_color, trans_intensity: trans_intensity,
-                  trans_color: trans_color, trans_eta: trans_eta}
+    /// Constructs a material which scatters light with bsdf, and neither glows nor absorbs
+    /// light.
+    pub fn new(bsdf: Box<Bsdf>) -> Material {
+        Material::from_surface(Surface::Bsdf(bsdf))
+    }
+
+    /// Constructs a material whose surface blends the surfaces of a and b, with weight (on
+    /// [0, 1]) of b. Only the surfaces are blended: the new material doesn't glow or absorb
+    /// light unless it is told to.
+    pub fn mix(a: Arc<Material>, b: Arc<Material>, weight: f64) -> Material {
+        assert!(weight >= 0.0 && weight <= 1.0, "Cannot mix materials by a weight outside [0, 1]!");
+        Material::mix_texture(a, b, Arc::new(weight))
+    }
+
+    /// Constructs a material like mix, with a weight which changes over space.
+    pub fn mix_texture(a: Arc<Material>, b: Arc<Material>, weight: Arc<Texture>) -> Material {
+        Material::from_surface(Surface::Mix {a: a, b: b, weight: weight})
+    }
+
+    /// Constructs a material which is base under a smooth clear coat (like varnish or the
+    /// lacquer on car paint) with index of refraction eta. The coat reflects light by the
+    /// Fresnel equations, and tints the light which goes through it (on the way in and out)
+    /// by color.
+    pub fn layered(base: Arc<Material>, eta: f64, color: Color) -> Material {
+        assert!(eta > 0.0, "Cannot have a coat with a non-positive index of refraction!");
+        Material::from_surface(Surface::Layered {base: base, eta: eta, color: color})
+    }
+
+    fn from_surface(surface: Surface) -> Material {
+        Material {surface: surface, absorption: Color::black(), emission: Color::black()}
+    }
+
+    /// Constructs a material from the intensities and colors of a matte, a glossy and a
+    /// transmissive part, the way Lux's materials used to be described. A glossy_power of -1
+    /// makes the glossy part a perfect mirror.
+    pub fn custom(matte_intensity: f64, matte_color: Color, glossy_intensity: f64,
+                  glossy_power: f64, glossy_color: Color,  trans_intensity: f64,
+                  trans_color: Color, trans_eta: f64) -> Material {
+        let mut bsdfs: Vec<Box<Bsdf>> = Vec::new();
+        let matte = matte_color * matte_intensity;
+        let glossy = glossy_color * glossy_intensity;
+
+        if glossy_intensity > 0.0 && glossy_power != -1.0 {
+            bsdfs.push(Box::new(Phong::new(matte, glossy, glossy_power)));
+        } else {
+            if matte_intensity > 0.0 {
+                bsdfs.push(Box::new(Lambertian::new(matte)));
+            }
+            if glossy_intensity > 0.0 {
+                bsdfs.push(Box::new(Mirror::new(glossy)));
+            }
+        }
+
+        if trans_intensity > 0.0 {
+            bsdfs.push(Box::new(Dielectric::new(trans_color * trans_intensity, trans_eta)));
+        }
+
+        Material::new(Box::new(Combined::new(bsdfs)))
     }
 
     // Returns a new "matte" material (100% matte_intensity).
     pub fn lambertian(color: Color) -> Material {
-        Material::new(1.0, color, 0.0, 0.0, color, 0.0, color, 1.0)
+        Material::new(Box::new(Lambertian::new(color)))
     }
 
     // Returns a new reflective color
     pub fn reflective(color: Color) -> Material {
-        Material::new(0.1, Color::black(), 0.9, -1.0, color, 0.0, color, 1.0)
+        Material::new(Box::new(Mirror::new(color * 0.9)))
     }
 
     pub fn glossy(color: Color, glossy_power: f64) -> Material {
-        Material::new(0.1, color, 0.9, glossy_power, color, 0.0, color, 1.0)
+        Material::new(Box::new(Phong::new(color * 0.1, color * 0.9, glossy_power)))
     }
 
     pub fn transparent(color: Color, trans_eta: f64) -> Material {
-        Material::new(0.05, color, 0.0, 0.0, color, 0.95, color, trans_eta)
+        Material::custom(0.05, color, 0.0, 0.0, color, 0.95, color, trans_eta)
+    }
+
+    /// Returns a new physically based material in the metallic/roughness style. metallic and
+    /// roughness both go from 0 to 1.
+    pub fn microfacet(base_color: Color, metallic: f64, roughness: f64) -> Material {
+        Material::new(Box::new(Microfacet::new(base_color, metallic, roughness)))
+    }
+
+    /// Returns a new clear dielectric, like glass or water, with index of refraction eta. Light
+    /// is split between reflection and refraction by the Fresnel equations, and refracted light
+    /// is tinted by color.
+    pub fn dielectric(color: Color, eta: f64) -> Material {
+        Material::new(Box::new(Dielectric::new(color, eta)))
+    }
+
+    /// Returns a new material which only glows, with color scaled by strength, and reflects
+    /// nothing.
+    pub fn emissive(color: Color, strength: f64) -> Material {
+        let mut material = Material::lambertian(Color::black());
+        material.set_emission(color, strength);
+        material
+    }
+
+    /// Makes light travelling through the inside of the material fade (by Beer-Lambert's law)
+    /// so that only color is left after distance. Thicker parts of the material look darker.
+    pub fn set_absorption(&mut self, color: Color, distance: f64) {
+        assert!(distance > 0.0, "Cannot absorb light over a non-positive distance!");
+        assert!(color.r > 0.0 && color.g > 0.0 && color.b > 0.0,
+                "Cannot absorb all of the light of a color!");
+
+        self.absorption = Color::new(-color.r.min(1.0).ln(), -color.g.min(1.0).ln(),
+                                     -color.b.min(1.0).ln()) * (1.0 / distance);
+    }
+
+    /// Returns the fraction of each color of light left after travelling distance through the
+    /// inside of the material.
+    pub fn transmittance(&self, distance: f64) -> Color {
+        let distance = distance as f32;
+        Color::new((-self.absorption.r * distance).exp(), (-self.absorption.g * distance).exp(),
+                   (-self.absorption.b * distance).exp())
+    }
+
+    /// Makes the material glow with color scaled by strength.
+    pub fn set_emission(&mut self, color: Color, strength: f64) {
+        assert!(strength >= 0.0, "Cannot have a material with a negative emission!");
+        self.emission = color * strength;
+    }
+
+    /// Whether the material gives off any light.
+    pub fn is_emissive(&self) -> bool {
+        self.emission.r > 0.0 || self.emission.g > 0.0 || self.emission.b > 0.0
+    }
+
+    /// Evaluates how the surface at position scatters light arriving from wi and leaving
+    /// towards wo, like Bsdf::eval.
+    pub fn eval(&self, wo: Vec3, wi: Vec3, norm: Vec3, position: Vec3) -> Color {
+        match self.surface {
+            Surface::Bsdf(ref bsdf) => bsdf.eval(wo, wi, norm),
+            Surface::Mix {ref a, ref b, ref weight} => {
+                let weight = weight.value(position);
+                a.eval(wo, wi, norm, position) * (1.0 - weight)
+                    + b.eval(wo, wi, norm, position) * weight
+            },
+            Surface::Layered {ref base, eta, color} => {
+                // Light gets through the coat on its way in and on its way out.
+                let through = (1.0 - fresnel(wo * norm, 1.0 / eta))
+                              * (1.0 - fresnel(wi * norm, 1.0 / eta));
+                base.eval(wo, wi, norm, position) * color * color * through
+            }
+        }
     }
+
+    /// Returns the probability density (per solid angle) of sample picking wi at position when
+    /// light leaves towards wo, like Bsdf::pdf.
+    pub fn pdf(&self, wo: Vec3, wi: Vec3, norm: Vec3, position: Vec3) -> f64 {
+        match self.surface {
+            Surface::Bsdf(ref bsdf) => bsdf.pdf(wo, wi, norm),
+            Surface::Mix {ref a, ref b, ref weight} => {
+                let weight = weight.value(position);
+                a.pdf(wo, wi, norm, position) * (1.0 - weight)
+                    + b.pdf(wo, wi, norm, position) * weight
+            },
+            Surface::Layered {ref base, eta, ..} => {
+                // Coat reflections are perfectly specular, so only the base is left.
+                (1.0 - fresnel(wo * norm, 1.0 / eta)) * base.pdf(wo, wi, norm, position)
+            }
+        }
+    }
+
+    /// Picks a direction for light to arrive from at position, for light leaving the surface
+    /// towards wo, like Bsdf::sample.
+    ///
+    /// Blends pick one of their materials by the weight, and coats reflect in proportion to
+    /// their Fresnel reflectance, passing the rest of the samples on to their base.
+    pub fn sample(&self, wo: Vec3, norm: Vec3, position: Vec3) -> Option<BsdfSample> {
+        let (sample, chance, scale) = match self.surface {
+            Surface::Bsdf(ref bsdf) => return bsdf.sample(wo, norm),
+            Surface::Mix {ref a, ref b, ref weight} => {
+                let weight = weight.value(position);
+                if jitter() < weight {
+                    (b.sample(wo, norm, position)?, weight, Color::white())
+                } else {
+                    (a.sample(wo, norm, position)?, 1.0 - weight, Color::white())
+                }
+            },
+            Surface::Layered {ref base, eta, color} => {
+                let reflectance = fresnel(wo * norm, 1.0 / eta);
+                if jitter() < reflectance {
+                    return Some(BsdfSample {direction: wo.reflect(facing(wo, norm)),
+                                            weight: Color::white(), pdf: reflectance,
+                                            specular: true});
+                }
+
+                let sample = base.sample(wo, norm, position)?;
+                let through = 1.0 - fresnel(sample.direction * norm, 1.0 / eta);
+                (sample, 1.0 - reflectance, color * color * through)
+            }
+        };
+
+        if sample.specular {
+            // The chance of picking this sample cancels out with how much of the light it
+            // carries, apart from what the coat takes.
+            return Some(BsdfSample {direction: sample.direction, weight: sample.weight * scale,
+                                    pdf: sample.pdf * chance, specular: true});
+        }
+
+        // Non-specular directions could have been picked the other way too.
+        let cos = (sample.direction * norm).abs();
+        let pdf = self.pdf(wo, sample.direction, norm, position);
+        if cos == 0.0 || pdf <= 0.0 {
+            return None;
+        }
+
+        Some(BsdfSample {direction: sample.direction,
+                         weight: self.eval(wo, sample.direction, norm, position) * (cos / pdf),
+                         pdf: pdf, specular: false})
+    }
+}
+
+/// How the surface of a Material scatters light.
+enum Surface {
+    /// A single BSDF.
+    Bsdf(Box<Bsdf>),
+    /// A blend of two materials, with weight of b.
+    Mix { a: Arc<Material>, b: Arc<Material>, weight: Arc<Texture> },
+    /// A clear coat with index of refraction eta and tint color over a base material.
+    Layered { base: Arc<Material>, eta: f64, color: Color }
 }
@@ -0,0 +1,226 @@
+use ::Arc;
+use std::io;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::fs::File;
+use geom::Vec3;
+use geom::Ray;
+use geom::AABB;
+use geom::random::jitter;
+use shape::Shape;
+use shape::SurfaceSample;
+use shape::Triangle;
+use shape::Intersection;
+use shape::Material;
+use scene::BVH;
+
+/// TriangleMesh is a collection of Triangles which share a single material, usually loaded from a
+/// Wavefront .obj file.
+///
+/// The Triangles are kept in a BVH of their own, so a mesh with many thousands of faces costs
+/// about as much to intersect as a handful of Spheres.
+pub struct TriangleMesh {
+    triangles: Vec<Triangle>,
+    bvh: BVH,
+    /// The running sum of the Triangles' areas, for picking Triangles by their area.
+    area_cdf: Vec<f64>,
+    material: Arc<Material>
+}
+
+impl TriangleMesh {
+    /// Constructs a mesh from a list of Triangles. Every Triangle is given the mesh's material.
+    pub fn new(triangles: Vec<Triangle>, material: Arc<Material>) -> TriangleMesh {
+        let bounds: Vec<AABB> = triangles.iter()
+                                         .map(|t| AABB::from_points(&t.get_vertices()))
+                                         .collect();
+        let mut total = 0.0;
+        let area_cdf = triangles.iter()
+                                .map(|t| { total += t.area(); total })
+                                .collect();
+        let mut mesh = TriangleMesh {triangles: triangles, bvh: BVH::new(&bounds),
+                                     area_cdf: area_cdf, material: material.clone()};
+        mesh.set_material(material);
+        mesh
+    }
+
+    /// Loads a mesh from the Wavefront .obj file at filename.
+    ///
+    /// Only vertex positions (v), vertex normals (vn) and faces (f) are read; everything else
+    /// (texture coordinates, groups, smoothing groups, .mtl references) is ignored. Polygons with
+    /// more than three vertices are split into a fan of Triangles. If every vertex of a face
+    /// references a normal, the face is smooth shaded.
+    pub fn load(filename: &str, material: Arc<Material>) -> io::Result<TriangleMesh> {
+        let file = File::open(filename)?;
+        TriangleMesh::read_obj(BufReader::new(file), material)
+    }
+
+    /// Reads a mesh in the Wavefront .obj format from any buffered reader.
+    pub fn read_obj<R: BufRead>(reader: R, material: Arc<Material>) -> io::Result<TriangleMesh> {
+        let mut positions = Vec::new();
+        let mut normals = Vec::new();
+        let mut triangles = Vec::new();
+
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line_number = index + 1;
+            // Everything after a '#' is a comment.
+            let line = line.split('#').next().unwrap_or("");
+            let mut tokens = line.split_whitespace();
+
+            match tokens.next() {
+                Some("v") => positions.push(parse_vec3(tokens, line_number)?),
+                Some("vn") => normals.push(parse_vec3(tokens, line_number)?.norm()),
+                Some("f") => {
+                    let mut face = Vec::new();
+                    for token in tokens {
+                        face.push(parse_face_vertex(token, positions.len(), normals.len(),
+                                                    line_number)?);
+                    }
+
+                    if face.len() < 3 {
+                        return Err(obj_error(line_number, "a face needs at least 3 vertices"));
+                    }
+
+                    let smooth = face.iter().all(|&(_, normal)| normal.is_some());
+
+                    // Triangulate the polygon as a fan around its first vertex.
+                    for i in 1..face.len() - 1 {
+                        let (a, b, c) = (face[0], face[i], face[i + 1]);
+
+                        triangles.push(if smooth {
+                            Triangle::with_normals(positions[a.0], positions[b.0], positions[c.0],
+                                                   normals[a.1.unwrap()], normals[b.1.unwrap()],
+                                                   normals[c.1.unwrap()], material.clone())
+                        } else {
+                            Triangle::new(positions[a.0], positions[b.0], positions[c.0],
+                                          material.clone())
+                        });
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        Ok(TriangleMesh::new(triangles, material))
+    }
+
+    /// Returns the number of Triangles in the mesh.
+    pub fn len(&self) -> usize {
+        self.triangles.len()
+    }
+
+    /// Returns a slice of all the Triangles in the mesh.
+    pub fn get_triangles(&self) -> &[Triangle] {
+        &self.triangles
+    }
+}
+
+impl Shape for TriangleMesh {
+    /// Returns the first, closest intersection of a Ray and any Triangle in the mesh.
+    fn intersect_first(&self, ray: Ray) -> Option<Intersection> {
+        self.bvh.intersect_first(&ray, |i| self.triangles[i].intersect_first(ray))
+    }
+
+    /// Returns a Vec containing every intersection of a Ray and the mesh, sorted by distance.
+    fn intersect_all(&self, ray: Ray) -> Vec<Intersection> {
+        let mut vec: Vec<Intersection> = self.triangles.iter()
+                                                       .filter_map(|t| t.intersect_first(ray))
+                                                       .collect();
+        vec.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
+        vec
+    }
+
+    /// Returns the box enclosing every Triangle in the mesh.
+    fn bounding_box(&self) -> Option<AABB> {
+        self.bvh.bounds()
+    }
+
+    /// Sets the material of the mesh and every Triangle in it.
+    fn set_material(&mut self, material: Arc<Material>) {
+        for triangle in self.triangles.iter_mut() {
+            triangle.set_material(material.clone());
+        }
+
+        self.material = material;
+    }
+
+    /// Gets the material of the mesh.
+    fn get_material(&self) -> Arc<Material> {
+        self.material.clone()
+    }
+
+    /// Picks a point evenly over the whole mesh, by picking a Triangle in proportion to its area
+    /// and then a point on it.
+    fn sample_surface(&self) -> Option<SurfaceSample> {
+        let total = match self.area_cdf.last() {
+            Some(&total) if total > 0.0 => total,
+            _ => return None
+        };
+
+        // Binary search for the first Triangle whose running sum is past the target.
+        let target = jitter() * total;
+        let (mut low, mut high) = (0, self.area_cdf.len() - 1);
+        while low < high {
+            let middle = (low + high) / 2;
+            if self.area_cdf[middle] > target {
+                high = middle;
+            } else {
+                low = middle + 1;
+            }
+        }
+
+        self.triangles[low].sample_surface().map(|sample| {
+            SurfaceSample {position: sample.position, norm: sample.norm, pdf: 1.0 / total}
+        })
+    }
+}
+
+/// Builds the io::Error returned for malformed .obj files.
+fn obj_error(line: usize, message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line, message))
+}
+
+/// Parses the three components following a "v" or "vn" statement.
+fn parse_vec3<'a, I: Iterator<Item = &'a str>>(mut tokens: I, line: usize) -> io::Result<Vec3> {
+    let mut components = [0.0; 3];
+
+    for component in components.iter_mut() {
+        let token = tokens.next().ok_or_else(|| obj_error(line, "expected 3 components"))?;
+        *component = token.parse().map_err(|_| obj_error(line, "invalid number"))?;
+    }
+
+    Ok(Vec3::new(components[0], components[1], components[2]))
+}
+
+/// Parses one "v", "v/vt", "v//vn" or "v/vt/vn" face vertex into zero-based position and normal
+/// indices. Negative indices count backwards from the most recently read element.
+fn parse_face_vertex(token: &str, num_positions: usize, num_normals: usize, line: usize)
+                     -> io::Result<(usize, Option<usize>)> {
+    let mut parts = token.split('/');
+
+    let position = resolve_index(parts.next(), num_positions, line)?
+                       .ok_or_else(|| obj_error(line, "face vertex is missing a position"))?;
+    // Texture coordinates are not used by Lux.
+    parts.next();
+    let normal = resolve_index(parts.next(), num_normals, line)?;
+
+    Ok((position, normal))
+}
+
+/// Turns a one-based (or negative, relative) .obj index into a zero-based index into a list of
+/// length len. Empty or missing indices resolve to None.
+fn resolve_index(token: Option<&str>, len: usize, line: usize) -> io::Result<Option<usize>> {
+    let token = match token {
+        Some(token) if !token.is_empty() => token,
+        _ => return Ok(None)
+    };
+
+    let index: i64 = token.parse().map_err(|_| obj_error(line, "invalid face index"))?;
+    let resolved = if index < 0 { len as i64 + index } else { index - 1 };
+
+    if resolved < 0 || resolved >= len as i64 {
+        return Err(obj_error(line, "face index out of range"));
+    }
+
+    Ok(Some(resolved as usize))
+}
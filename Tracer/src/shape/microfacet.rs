@@ -0,0 +1,205 @@
+use std::f64::consts::PI;
+use geom::Color;
+use geom::Vec3;
+use geom::random::jitter;
+use shape::bsdf::{Bsdf, BsdfSample, facing};
+
+/// The smallest GGX roughness (alpha) used, since perfectly smooth microfacets would make the
+/// distribution infinitely thin.
+const MIN_ALPHA: f64 = 1.0e-3;
+
+/// The reflectance of dielectrics (like plastic or paint) looking straight at them, which is
+/// about 4% for most of them.
+const DIELECTRIC_REFLECTANCE: f32 = 0.04;
+
+/// Microfacet is a physically based surface in the "metallic/roughness" style used by most
+/// other renderers and texturing tools.
+///
+/// The surface is made of tiny mirror-like facets, whose normals follow the GGX (Trowbridge-
+/// Reitz) distribution and which shadow and mask each other following Smith's height-correlated
+/// function. How much each facet reflects comes from Schlick's approximation of the Fresnel
+/// equations. Dielectrics (metallic = 0) also have a matte base under their reflections, while
+/// metals (metallic = 1) tint their reflections with the base color.
+pub struct Microfacet {
+    base_color: Color,
+    metallic: f64,
+    roughness: f64
+}
+
+impl Microfacet {
+    /// Constructs a microfacet surface. metallic and roughness both go from 0 to 1; roughness is
+    /// perceptual, so the GGX alpha is its square.
+    pub fn new(base_color: Color, metallic: f64, roughness: f64) -> Microfacet {
+        assert!(metallic >= 0.0 && metallic <= 1.0, "Cannot have a metallic outside of [0, 1]!");
+        assert!(roughness >= 0.0 && roughness <= 1.0,
+                "Cannot have a roughness outside of [0, 1]!");
+
+        Microfacet {base_color: base_color, metallic: metallic, roughness: roughness}
+    }
+
+    /// The GGX alpha of the surface.
+    fn alpha(&self) -> f64 {
+        (self.roughness * self.roughness).max(MIN_ALPHA)
+    }
+
+    /// The reflectance of the facets looking straight at them.
+    fn reflectance(&self) -> Color {
+        let dielectric = Color::new(DIELECTRIC_REFLECTANCE, DIELECTRIC_REFLECTANCE,
+                                    DIELECTRIC_REFLECTANCE);
+        dielectric * (1.0 - self.metallic) + self.base_color * self.metallic
+    }
+
+    /// Schlick's approximation of the Fresnel reflectance at an angle with cosine cos.
+    fn fresnel(&self, cos: f64) -> Color {
+        let f0 = self.reflectance();
+        f0 + (Color::white() - f0) * (1.0 - cos.max(0.0).min(1.0)).powi(5)
+    }
+
+    /// The chance of sampling a reflection rather than the matte base, in proportion to how
+    /// much light each gives back towards a direction with cosine cos_o.
+    fn specular_probability(&self, cos_o: f64) -> f64 {
+        let specular = self.fresnel(cos_o).luminance();
+        let diffuse = (1.0 - specular) * (1.0 - self.metallic) * self.base_color.luminance();
+
+        if specular + diffuse <= 0.0 {
+            return 1.0;
+        }
+
+        specular / (specular + diffuse)
+    }
+}
+
+impl Bsdf for Microfacet {
+    fn eval(&self, wo: Vec3, wi: Vec3, norm: Vec3) -> Color {
+        let norm = facing(wo, norm);
+        let cos_o = wo * norm;
+        let cos_i = wi * norm;
+        if cos_o <= 0.0 || cos_i <= 0.0 {
+            return Color::black();
+        }
+
+        let h = (wo + wi).norm();
+        let fresnel = self.fresnel(wo * h);
+        let alpha = self.alpha();
+
+        let specular = fresnel * (distribution(h * norm, alpha) * smith(cos_o, cos_i, alpha)
+                                  / (4.0 * cos_o * cos_i));
+        // Light which isn't reflected by the facets goes on to the matte base, which metals
+        // don't have.
+        let diffuse = (Color::white() - fresnel) * self.base_color
+                      * ((1.0 - self.metallic) / PI);
+
+        specular + diffuse
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3, norm: Vec3) -> f64 {
+        let norm = facing(wo, norm);
+        let cos_o = wo * norm;
+        let cos_i = wi * norm;
+        if cos_o <= 0.0 || cos_i <= 0.0 {
+            return 0.0;
+        }
+
+        let h = (wo + wi).norm();
+        let alpha = self.alpha();
+        let specular = self.specular_probability(cos_o);
+
+        // Visible normals are picked with density G1(wo) D(h) (wo . h) / cos_o, and reflecting
+        // about h turns that into a density of directions by dividing by 4 (wo . h).
+        let visible = smith_g1(cos_o, alpha) * distribution(h * norm, alpha) / (4.0 * cos_o);
+
+        specular * visible + (1.0 - specular) * cos_i / PI
+    }
+
+    /// Reflections are sampled by the normals of the facets visible from wo (Heitz, "Sampling
+    /// the GGX Distribution of Visible Normals", 2018), and the matte base by the cosine.
+    fn sample(&self, wo: Vec3, norm: Vec3) -> Option<BsdfSample> {
+        let norm = facing(wo, norm);
+        let cos_o = wo * norm;
+        if cos_o <= 0.0 {
+            return None;
+        }
+
+        let (u, v) = norm.basis();
+
+        let wi = if jitter() < self.specular_probability(cos_o) {
+            let alpha = self.alpha();
+            // The sampling works with the normal along z, in a frame stretched by alpha.
+            let local = Vec3::new(wo * u, wo * v, cos_o);
+            let h = sample_visible_normal(local, alpha);
+            let h = u * h.x + v * h.y + norm * h.z;
+            wo.reflect(h)
+        } else {
+            let local = Vec3::sample_hemisphere(1.0);
+            u * local.x + norm * local.y + v * local.z
+        };
+
+        if wi * norm <= 0.0 {
+            return None;
+        }
+
+        BsdfSample::scattered(self, wo, wi, norm)
+    }
+
+    fn albedo(&self) -> f64 {
+        let specular = self.reflectance().luminance();
+        specular + (1.0 - specular) * (1.0 - self.metallic) * self.base_color.luminance()
+    }
+}
+
+/// The GGX distribution of facet normals, where cos is the cosine of the facet normal with the
+/// surface normal.
+fn distribution(cos: f64, alpha: f64) -> f64 {
+    if cos <= 0.0 {
+        return 0.0;
+    }
+
+    let alpha2 = alpha * alpha;
+    let d = cos * cos * (alpha2 - 1.0) + 1.0;
+    alpha2 / (PI * d * d)
+}
+
+/// Smith's lambda for GGX, which measures how much of the surface is hidden from a direction
+/// with cosine cos.
+fn lambda(cos: f64, alpha: f64) -> f64 {
+    let tan2 = (1.0 - cos * cos).max(0.0) / (cos * cos);
+    ((1.0 + alpha * alpha * tan2).sqrt() - 1.0) / 2.0
+}
+
+/// The fraction of facets which are seen from a direction with cosine cos.
+fn smith_g1(cos: f64, alpha: f64) -> f64 {
+    1.0 / (1.0 + lambda(cos, alpha))
+}
+
+/// The fraction of facets which are seen from both directions, taking into account that a facet
+/// high up on the surface is more likely to be seen from both.
+fn smith(cos_o: f64, cos_i: f64, alpha: f64) -> f64 {
+    1.0 / (1.0 + lambda(cos_o, alpha) + lambda(cos_i, alpha))
+}
+
+/// Picks a facet normal seen from wo (in a frame with the normal along z), with a probability
+/// in proportion to how much of it is visible.
+fn sample_visible_normal(wo: Vec3, alpha: f64) -> Vec3 {
+    // Stretch the view so the facets become a hemisphere.
+    let view = Vec3::new(alpha * wo.x, alpha * wo.y, wo.z).norm();
+
+    let length2 = view.x * view.x + view.y * view.y;
+    let t1 = if length2 > 0.0 {
+        Vec3::new(-view.y, view.x, 0.0) * (1.0 / length2.sqrt())
+    } else {
+        Vec3::new(1.0, 0.0, 0.0)
+    };
+    let t2 = view % t1;
+
+    // Pick a point on the disk that the visible half of the hemisphere projects onto.
+    let r = jitter().sqrt();
+    let phi = 2.0 * PI * jitter();
+    let p1 = r * phi.cos();
+    let s = 0.5 * (1.0 + view.z);
+    let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+
+    let normal = t1 * p1 + t2 * p2 + view * (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt();
+
+    // Unstretch the normal.
+    Vec3::new(alpha * normal.x, alpha * normal.y, normal.z.max(0.0)).norm()
+}
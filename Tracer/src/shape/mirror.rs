@@ -0,0 +1,34 @@
+use geom::Color;
+use geom::Vec3;
+use shape::bsdf::{Bsdf, BsdfSample, facing};
+
+/// Mirror is a perfectly smooth reflector, which reflects all light in the mirror direction.
+pub struct Mirror {
+    color: Color
+}
+
+impl Mirror {
+    /// Constructs a mirror which reflects color.
+    pub fn new(color: Color) -> Mirror {
+        Mirror {color: color}
+    }
+}
+
+impl Bsdf for Mirror {
+    fn eval(&self, _: Vec3, _: Vec3, _: Vec3) -> Color {
+        Color::black()
+    }
+
+    fn pdf(&self, _: Vec3, _: Vec3, _: Vec3) -> f64 {
+        0.0
+    }
+
+    fn sample(&self, wo: Vec3, norm: Vec3) -> Option<BsdfSample> {
+        Some(BsdfSample {direction: wo.reflect(facing(wo, norm)), weight: self.color, pdf: 1.0,
+                         specular: true})
+    }
+
+    fn albedo(&self) -> f64 {
+        self.color.luminance()
+    }
+}
@@ -3,17 +3,51 @@ pub mod intersection;
 pub mod light;
 pub mod point_light;
 pub mod direction_light;
+pub mod spot_light;
+pub mod ies;
+pub mod rect_light;
+pub mod disk_light;
+pub mod sphere_light;
+pub mod shape_light;
 pub mod material;
+pub mod bsdf;
+pub mod lambertian;
+pub mod phong;
+pub mod mirror;
+pub mod dielectric;
+pub mod microfacet;
+pub mod texture;
 pub mod sphere;
 pub mod plane;
 pub mod cylinder;
+pub mod triangle;
+pub mod mesh;
+pub mod instance;
+pub mod csg;
 
-pub use shape::shape::Shape;
+pub use shape::shape::{Shape, SurfaceSample};
 pub use shape::intersection::Intersection;
-pub use shape::light::Light;
-pub use shape::point_light::PointLight;
+pub use shape::light::{Light, LightSample};
+pub use shape::point_light::{Attenuation, PointLight};
 pub use shape::direction_light::DirectionLight;
+pub use shape::spot_light::SpotLight;
+pub use shape::ies::IesProfile;
+pub use shape::rect_light::RectLight;
+pub use shape::disk_light::DiskLight;
+pub use shape::sphere_light::SphereLight;
+pub use shape::shape_light::ShapeLight;
 pub use shape::material::Material;
+pub use shape::bsdf::{Bsdf, BsdfSample, Combined};
+pub use shape::lambertian::Lambertian;
+pub use shape::phong::Phong;
+pub use shape::mirror::Mirror;
+pub use shape::dielectric::Dielectric;
+pub use shape::microfacet::Microfacet;
+pub use shape::texture::{Checker, Noise, Texture};
 pub use shape::sphere::Sphere;
 pub use shape::plane::Plane;
 pub use shape::cylinder::Cylinder;
+pub use shape::triangle::Triangle;
+pub use shape::mesh::TriangleMesh;
+pub use shape::instance::Instance;
+pub use shape::csg::{Csg, CsgOperation};
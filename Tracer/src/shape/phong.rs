@@ -0,0 +1,86 @@
+use std::f64::consts::PI;
+use geom::Color;
+use geom::Vec3;
+use geom::random::jitter;
+use shape::bsdf::{Bsdf, BsdfSample, facing};
+
+/// Phong is a plastic-like surface: a matte base with a glossy highlight around the mirror
+/// direction, which is the (energy normalized) Phong lobe. A higher power gives a tighter,
+/// shinier highlight.
+pub struct Phong {
+    diffuse: Color,
+    specular: Color,
+    power: f64
+}
+
+impl Phong {
+    /// Constructs a surface with a matte base which reflects diffuse and a highlight which
+    /// reflects specular, with the given power.
+    pub fn new(diffuse: Color, specular: Color, power: f64) -> Phong {
+        assert!(power >= 0.0, "Cannot have a glossy highlight with a negative power!");
+        Phong {diffuse: diffuse, specular: specular, power: power}
+    }
+
+    /// Returns the chance of sampling the highlight rather than the matte base.
+    fn specular_chance(&self) -> f64 {
+        let diffuse = self.diffuse.luminance();
+        let specular = self.specular.luminance();
+
+        if diffuse + specular <= 0.0 {
+            return 0.0;
+        }
+
+        specular / (diffuse + specular)
+    }
+}
+
+impl Bsdf for Phong {
+    fn eval(&self, wo: Vec3, wi: Vec3, norm: Vec3) -> Color {
+        // Both lobes only reflect, so wo and wi must be on the same side of the surface.
+        if (wo * norm) * (wi * norm) <= 0.0 {
+            return Color::black();
+        }
+
+        let cos_alpha = (wo.reflect(facing(wo, norm)) * wi).max(0.0);
+
+        self.diffuse * (1.0 / PI)
+            + self.specular * ((self.power + 2.0) / (2.0 * PI) * cos_alpha.powf(self.power))
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3, norm: Vec3) -> f64 {
+        if (wo * norm) * (wi * norm) <= 0.0 {
+            return 0.0;
+        }
+
+        let specular = self.specular_chance();
+        let cos_alpha = (wo.reflect(facing(wo, norm)) * wi).max(0.0);
+
+        (1.0 - specular) * (wi * norm).abs() / PI
+            + specular * (self.power + 1.0) / (2.0 * PI) * cos_alpha.powf(self.power)
+    }
+
+    /// Picks directions from either the cosine around the normal or the Phong lobe around the
+    /// mirror direction.
+    fn sample(&self, wo: Vec3, norm: Vec3) -> Option<BsdfSample> {
+        let norm = facing(wo, norm);
+        let (axis, power) = if jitter() < self.specular_chance() {
+            (wo.reflect(norm), self.power)
+        } else {
+            (norm, 1.0)
+        };
+
+        let (u, v) = axis.basis();
+        let local = Vec3::sample_hemisphere(power);
+        let wi = u * local.x + axis * local.y + v * local.z;
+
+        if wi * norm <= 0.0 {
+            return None;
+        }
+
+        BsdfSample::scattered(self, wo, wi, norm)
+    }
+
+    fn albedo(&self) -> f64 {
+        self.diffuse.luminance() + self.specular.luminance()
+    }
+}
@@ -2,6 +2,7 @@ use ::Arc;
 use geom::Vec3;
 use geom::Color;
 use geom::Ray;
+use geom::AABB;
 use shape::Shape;
 use shape::Intersection;
 use shape::Material;
@@ -49,6 +50,11 @@ impl Shape for Plane {
         }
     }
 
+    /// A Plane is infinite, so it has no bounding box.
+    fn bounding_box(&self) -> Option<AABB> {
+        None
+    }
+
     /// Sets the material of the Plane.
     fn set_material(&mut self, material: Arc<Material>) {
         self.material = material;
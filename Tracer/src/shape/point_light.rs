@@ -1,38 +1,92 @@
+use ::Arc;
 use geom::Vec3;
 use geom::Color;
-use geom::Ray;
 use shape::Light;
-use shape::Intersection;
-use scene::World;
+use shape::LightSample;
+use shape::IesProfile;
+
+/// Attenuation describes how the light from a point fades with distance d, as
+/// 1 / (constant + linear * d + quadratic * d²).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Attenuation {
+    constant: f64,
+    linear: f64,
+    quadratic: f64
+}
+
+impl Attenuation {
+    pub fn new(constant: f64, linear: f64, quadratic: f64) -> Attenuation {
+        assert!(constant >= 0.0 && linear >= 0.0 && quadratic >= 0.0,
+                "Cannot have a negative attenuation!");
+        assert!(constant > 0.0 || linear > 0.0 || quadratic > 0.0,
+                "Cannot have an attenuation which is all zero!");
+        Attenuation {constant: constant, linear: linear, quadratic: quadratic}
+    }
+
+    /// The physically correct falloff with the square of the distance.
+    pub fn inverse_square() -> Attenuation {
+        Attenuation {constant: 0.0, linear: 0.0, quadratic: 1.0}
+    }
+
+    /// No falloff at all, so the light is equally bright at any distance.
+    pub fn none() -> Attenuation {
+        Attenuation {constant: 1.0, linear: 0.0, quadratic: 0.0}
+    }
+
+    /// Returns the fraction of the light left after distance.
+    pub fn factor(&self, distance: f64) -> f64 {
+        1.0 / (self.constant + (self.linear + self.quadratic * distance) * distance)
+    }
+}
 
 /// PointLight represents a single, infinitely dense (1 dimensional) light source in the scene
+///
+/// It shines equally in every direction, unless it is given an IesProfile. Its light fades with
+/// the square of the distance, so its color times its intensity is how bright it is 1 unit away.
 pub struct PointLight {
     position: Vec3,
-    color: Color
+    color: Color,
+    intensity: f64,
+    attenuation: Attenuation,
+    profile: Option<(Arc<IesProfile>, Vec3)>
 }
 
 impl PointLight {
     pub fn new(position: Vec3, color: Color) -> PointLight {
-        PointLight {position: position, color: color}
+        PointLight {position: position, color: color, intensity: 1.0,
+                    attenuation: Attenuation::inverse_square(), profile: None}
+    }
+
+    /// Sets how strongly the light shines, separately from its color. Defaults to 1.
+    pub fn set_intensity(&mut self, intensity: f64) {
+        assert!(intensity >= 0.0, "Cannot have a light with a negative intensity!");
+        self.intensity = intensity;
+    }
+
+    /// Replaces the light's falloff with distance, which is inverse square by default.
+    pub fn set_attenuation(&mut self, attenuation: Attenuation) {
+        self.attenuation = attenuation;
+    }
+
+    /// Shapes the light with a measured profile, with the fixture's axis pointing along axis.
+    pub fn set_profile(&mut self, profile: Arc<IesProfile>, axis: Vec3) {
+        self.profile = Some((profile, axis.norm()));
     }
 }
 
 impl Light for PointLight {
-    fn in_shadow(&self, world: &World, intersection: &Intersection) -> Option<Vec3> {
-        // We construct a ray from the point of intersection to the light. If it
-        // intersects an object, we know there is no light getting to the object.
-        // We use step_epsilon() so the Ray must not intersect from the object it is
-        // being emitted from. Without it, we have a lot of black-dotted noise.
-        let L = self.position - intersection.position;
-        let shadow_ray = Ray::new(intersection.position, L).step_epsilon();
-
-        if let Some(shadow) = world.get_closest_intersection(&shadow_ray) {
-            if shadow.distance < L.mag() {
-                return None;
-            }
+    fn sample(&self, position: Vec3) -> Option<LightSample> {
+        let L = self.position - position;
+        let distance = L.mag();
+        let direction = L * (1.0 / distance);
+
+        let mut intensity = self.intensity * self.attenuation.factor(distance);
+        if let Some((ref profile, axis)) = self.profile {
+            intensity *= profile.intensity_along(axis, -direction);
         }
 
-        Some(L)
+        Some(LightSample {direction: direction, distance: distance,
+                          radiance: self.color * intensity, pdf: 1.0})
     }
 
     fn get_color(&self) -> Color {
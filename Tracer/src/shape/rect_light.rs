@@ -0,0 +1,66 @@
+use geom::Vec3;
+use geom::Color;
+use geom::random::jitter;
+use shape::Light;
+use shape::LightSample;
+
+/// The default number of shadow rays traced towards an area light.
+const SAMPLES: u32 = 16;
+
+/// RectLight is a parallelogram which glows evenly over its surface, casting soft shadows.
+///
+/// It only shines from its front side, which is the side (u x v) points towards.
+pub struct RectLight {
+    center: Vec3,
+    u: Vec3,
+    v: Vec3,
+    color: Color,
+    samples: u32
+}
+
+impl RectLight {
+    /// Constructs a RectLight centered on center, whose edges are u and v.
+    pub fn new(center: Vec3, u: Vec3, v: Vec3, color: Color) -> RectLight {
+        RectLight {center: center, u: u, v: v, color: color, samples: SAMPLES}
+    }
+
+    /// Sets the number of shadow rays traced towards the light from each lit point. More rays
+    /// give smoother shadows.
+    pub fn set_samples(&mut self, samples: u32) {
+        assert!(samples > 0, "Cannot sample a light with no rays!");
+        self.samples = samples;
+    }
+}
+
+impl Light for RectLight {
+    fn sample(&self, position: Vec3) -> Option<LightSample> {
+        let point = self.center + self.u * (jitter() - 0.5) + self.v * (jitter() - 0.5);
+        let normal = self.u % self.v;
+        let area = normal.mag();
+
+        let L = point - position;
+        let distance = L.mag();
+        let direction = L * (1.0 / distance);
+        let cos = -(direction * normal) / area;
+
+        if cos <= 0.0 {
+            return None;
+        }
+
+        // Points are picked evenly over the area, so turn the pdf into one over solid angle.
+        Some(LightSample {direction: direction, distance: distance, radiance: self.color,
+                          pdf: distance * distance / (area * cos)})
+    }
+
+    fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    fn get_color(&self) -> Color {
+        self.color
+    }
+
+    fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+}
@@ -1,7 +1,17 @@
 use ::Arc;
 use shape::Intersection;
 use shape::Material;
+use geom::Vec3;
 use geom::Ray;
+use geom::AABB;
+
+/// SurfaceSample is a point picked on the surface of a Shape.
+pub struct SurfaceSample {
+    pub position: Vec3,
+    pub norm: Vec3,
+    /// The probability density (per unit of area) of having picked the point.
+    pub pdf: f64
+}
 
 /// Shape is the trait which all geometric shapes which interact in the scene are expected to
 /// implement.
@@ -16,9 +26,20 @@ pub trait Shape : Send + Sync {
     /// Returns a Vec containing all possible intersections of a Ray and a Shape.
     fn intersect_all(&self, ray: Ray) -> Vec<Intersection>;
 
+    /// Returns an axis-aligned box enclosing the whole Shape, or None if the Shape is unbounded
+    /// (like a Plane).
+    fn bounding_box(&self) -> Option<AABB>;
+
     /// Sets the material of the Shape.
     fn set_material(&mut self, material: Arc<Material>);
 
     /// Gets the material of the Shape.
     fn get_material(&self) -> Arc<Material>;
+
+    /// Picks a point evenly over the surface of the Shape, so that glowing shapes can be sampled
+    /// as lights. Shapes which can't be sampled (like unbounded ones) return None, which is the
+    /// default.
+    fn sample_surface(&self) -> Option<SurfaceSample> {
+        None
+    }
 }
@@ -0,0 +1,103 @@
+use ::Arc;
+use geom::Vec3;
+use geom::Color;
+use shape::Intersection;
+use shape::Light;
+use shape::LightSample;
+use shape::Shape;
+
+/// The default number of shadow rays traced towards an area light.
+const SAMPLES: u32 = 16;
+
+/// Shadow rays stop this fraction of the way short of the sampled point, so they don't hit the
+/// glowing shape itself.
+const SHADOW_EPSILON: f64 = 1.0e-6;
+
+/// ShapeLight lights the scene with a Shape whose material glows, like a neon tube or a light
+/// panel. Points are sampled evenly over the Shape's surface, so it casts soft shadows like the
+/// other area lights.
+///
+/// Use World::add_emitter to add a glowing Shape to a World along with its ShapeLight, which
+/// also lets rays which hit the Shape know that it is sampled as a light.
+pub struct ShapeLight {
+    shape: Arc<Shape>,
+    color: Color,
+    samples: u32,
+    /// The probability density (per unit of area) of sampling any point on the shape, which
+    /// is the same everywhere since shapes are sampled evenly.
+    area_pdf: f64
+}
+
+impl ShapeLight {
+    /// Constructs a light from a Shape, which glows with its material's emission. The Shape
+    /// must be able to sample its surface.
+    pub fn new(shape: Arc<Shape>) -> ShapeLight {
+        let area_pdf = match shape.sample_surface() {
+            Some(point) => point.pdf,
+            None => panic!("Cannot light a scene with an unsampled shape!")
+        };
+
+        let color = shape.get_material().emission;
+        ShapeLight {shape: shape, color: color, samples: SAMPLES, area_pdf: area_pdf}
+    }
+
+    /// Sets the number of shadow rays traced towards the light from each lit point. More rays
+    /// give smoother shadows.
+    pub fn set_samples(&mut self, samples: u32) {
+        assert!(samples > 0, "Cannot sample a light with no rays!");
+        self.samples = samples;
+    }
+
+    /// Returns the probability density (per solid angle) of sample picking the point where
+    /// intersection hit the shape, when lighting position.
+    pub fn pdf(&self, position: Vec3, intersection: &Intersection) -> f64 {
+        let L = intersection.position - position;
+        let distance2 = L * L;
+        let cos = (L * intersection.norm).abs() / distance2.sqrt();
+
+        if distance2 == 0.0 || cos == 0.0 {
+            return 0.0;
+        }
+
+        self.area_pdf * distance2 / cos
+    }
+}
+
+impl Light for ShapeLight {
+    fn sample(&self, position: Vec3) -> Option<LightSample> {
+        let point = match self.shape.sample_surface() {
+            Some(point) => point,
+            None => return None
+        };
+
+        let L = point.position - position;
+        let distance = L.mag();
+        let direction = L * (1.0 / distance);
+        // Glowing surfaces shine from both of their sides.
+        let cos = (direction * point.norm).abs();
+
+        if distance == 0.0 || cos == 0.0 {
+            return None;
+        }
+
+        // Points are picked evenly over the area, so turn the pdf into one over solid angle.
+        Some(LightSample {direction: direction, distance: distance * (1.0 - SHADOW_EPSILON),
+                          radiance: self.color, pdf: point.pdf * distance * distance / cos})
+    }
+
+    fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    fn is_visible(&self) -> bool {
+        true
+    }
+
+    fn get_color(&self) -> Color {
+        self.color
+    }
+
+    fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+}
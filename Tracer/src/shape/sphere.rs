@@ -1,7 +1,11 @@
 use ::Arc;
+use std::f64::consts::PI;
 use geom::Vec3;
+use geom::random::jitter;
 use geom::Ray;
+use geom::AABB;
 use shape::Shape;
+use shape::SurfaceSample;
 use shape::Intersection;
 use shape::Material;
 
@@ -90,6 +94,12 @@ impl Shape for Sphere {
         ret
     }
 
+    /// Returns the cube enclosing the Sphere.
+    fn bounding_box(&self) -> Option<AABB> {
+        let r = Vec3::new(self.radius, self.radius, self.radius);
+        Some(AABB::new(self.position - r, self.position + r))
+    }
+
     /// Sets the material of the Sphere.
     fn set_material(&mut self, material: Arc<Material>) {
         self.material = material;
@@ -99,4 +109,16 @@ impl Shape for Sphere {
     fn get_material(&self) -> Arc<Material> {
         self.material.clone()
     }
+
+    /// Picks a point evenly over the Sphere.
+    fn sample_surface(&self) -> Option<SurfaceSample> {
+        // Archimedes: the height of an even point on a sphere is evenly distributed.
+        let y = 1.0 - 2.0 * jitter();
+        let r = (1.0 - y * y).max(0.0).sqrt();
+        let phi = 2.0 * PI * jitter();
+        let norm = Vec3::new(r * phi.cos(), y, r * phi.sin());
+
+        Some(SurfaceSample {position: self.position + norm * self.radius, norm: norm,
+                            pdf: 1.0 / (4.0 * PI * self.radius * self.radius)})
+    }
 }
@@ -0,0 +1,74 @@
+use std::f64::consts::PI;
+use geom::Vec3;
+use geom::Color;
+use geom::random::jitter;
+use shape::Light;
+use shape::LightSample;
+
+/// The default number of shadow rays traced towards an area light.
+const SAMPLES: u32 = 16;
+
+/// SphereLight is a glowing ball, which casts soft shadows (unlike a PointLight) whose size
+/// depends on the radius of the ball.
+pub struct SphereLight {
+    center: Vec3,
+    radius: f64,
+    color: Color,
+    samples: u32
+}
+
+impl SphereLight {
+    pub fn new(center: Vec3, radius: f64, color: Color) -> SphereLight {
+        SphereLight {center: center, radius: radius, color: color, samples: SAMPLES}
+    }
+
+    /// Sets the number of shadow rays traced towards the light from each lit point. More rays
+    /// give smoother shadows.
+    pub fn set_samples(&mut self, samples: u32) {
+        assert!(samples > 0, "Cannot sample a light with no rays!");
+        self.samples = samples;
+    }
+}
+
+impl Light for SphereLight {
+    fn sample(&self, position: Vec3) -> Option<LightSample> {
+        let L = self.center - position;
+        let center_distance = L.mag();
+
+        // Points inside the light aren't lit by it.
+        if center_distance <= self.radius {
+            return None;
+        }
+
+        // Only the cone of directions which hit the sphere is sampled, evenly over solid angle.
+        let sin2_max = (self.radius / center_distance).powi(2);
+        let cos_max = (1.0 - sin2_max).sqrt();
+        let cos = 1.0 - jitter() * (1.0 - cos_max);
+        let sin = (1.0 - cos * cos).max(0.0).sqrt();
+        let phi = 2.0 * PI * jitter();
+
+        let axis = L * (1.0 / center_distance);
+        let (a, b) = axis.basis();
+        let direction = axis * cos + a * (sin * phi.cos()) + b * (sin * phi.sin());
+
+        // The distance to the near side of the sphere along the sampled direction.
+        let distance = center_distance * cos
+                       - (self.radius * self.radius
+                          - center_distance * center_distance * sin * sin).max(0.0).sqrt();
+
+        Some(LightSample {direction: direction, distance: distance, radiance: self.color,
+                          pdf: 1.0 / (2.0 * PI * (1.0 - cos_max))})
+    }
+
+    fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    fn get_color(&self) -> Color {
+        self.color
+    }
+
+    fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+}
@@ -0,0 +1,90 @@
+use ::Arc;
+use geom::Vec3;
+use geom::Color;
+use shape::Light;
+use shape::LightSample;
+use shape::IesProfile;
+use shape::Attenuation;
+
+/// SpotLight is a point of light which only shines in a cone around its direction.
+///
+/// Inside the inner angle the light is at full strength, and it fades out smoothly towards the
+/// outer angle, past which nothing is lit. Like a PointLight, its light fades with the square
+/// of the distance unless it is given another attenuation.
+pub struct SpotLight {
+    position: Vec3,
+    direction: Vec3,
+    cos_inner: f64,
+    cos_outer: f64,
+    color: Color,
+    intensity: f64,
+    attenuation: Attenuation,
+    profile: Option<Arc<IesProfile>>
+}
+
+impl SpotLight {
+    /// Constructs a SpotLight at position pointing towards direction. The cone's inner and outer
+    /// angles are measured in radians from the direction to the edge of the cone.
+    pub fn new(position: Vec3, direction: Vec3, inner: f64, outer: f64, color: Color)
+               -> SpotLight {
+        assert!(inner >= 0.0 && inner <= outer, "A spot light's inner cone must fit in its outer \
+                                                 cone!");
+        SpotLight {position: position, direction: direction.norm(), cos_inner: inner.cos(),
+                   cos_outer: outer.cos(), color: color, intensity: 1.0,
+                   attenuation: Attenuation::inverse_square(), profile: None}
+    }
+
+    /// Sets how strongly the light shines, separately from its color. Defaults to 1.
+    pub fn set_intensity(&mut self, intensity: f64) {
+        assert!(intensity >= 0.0, "Cannot have a light with a negative intensity!");
+        self.intensity = intensity;
+    }
+
+    /// Replaces the light's falloff with distance, which is inverse square by default.
+    pub fn set_attenuation(&mut self, attenuation: Attenuation) {
+        self.attenuation = attenuation;
+    }
+
+    /// Shapes the light with a measured profile, with the fixture's axis along the light's
+    /// direction. The cone still limits where the light shines.
+    pub fn set_profile(&mut self, profile: Arc<IesProfile>) {
+        self.profile = Some(profile);
+    }
+}
+
+impl Light for SpotLight {
+    fn sample(&self, position: Vec3) -> Option<LightSample> {
+        let L = self.position - position;
+        let distance = L.mag();
+        let direction = L * (1.0 / distance);
+        let cos = -direction * self.direction;
+
+        if cos <= self.cos_outer {
+            return None;
+        }
+
+        // Smoothstep between the edges of the cone.
+        let t = if self.cos_inner > self.cos_outer {
+            ((cos - self.cos_outer) / (self.cos_inner - self.cos_outer)).min(1.0)
+        } else {
+            1.0
+        };
+        let mut intensity = t * t * (3.0 - 2.0 * t) * self.intensity
+                            * self.attenuation.factor(distance);
+
+        if let Some(ref profile) = self.profile {
+            intensity *= profile.intensity_along(self.direction, -direction);
+        }
+
+        Some(LightSample {direction: direction, distance: distance,
+                          radiance: self.color * intensity, pdf: 1.0})
+    }
+
+    fn get_color(&self) -> Color {
+        self.color
+    }
+
+    fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+}
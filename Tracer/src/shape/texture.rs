@@ -0,0 +1,101 @@
+use geom::Vec3;
+
+/// A Texture is a number (on [0, 1]) which changes over space, like the weight that blends two
+/// materials together. Textures are solid: they are looked up by the position of a point in the
+/// world, so they don't need any texture coordinates on the surface.
+pub trait Texture: Send + Sync {
+    /// Returns the value of the texture at position.
+    fn value(&self, position: Vec3) -> f64;
+}
+
+/// A plain number is a texture which is the same everywhere.
+impl Texture for f64 {
+    fn value(&self, _: Vec3) -> f64 {
+        *self
+    }
+}
+
+/// Checker alternates between 0 and 1 in cubes of a given size, like a 3-dimensional
+/// checkerboard.
+pub struct Checker {
+    size: f64
+}
+
+impl Checker {
+    pub fn new(size: f64) -> Checker {
+        assert!(size > 0.0, "Cannot make a checker with empty cubes!");
+        Checker {size: size}
+    }
+}
+
+impl Texture for Checker {
+    fn value(&self, position: Vec3) -> f64 {
+        let cell = |x: f64| (x / self.size).floor() as i64;
+        let parity = cell(position.x) + cell(position.y) + cell(position.z);
+
+        if parity % 2 == 0 { 0.0 } else { 1.0 }
+    }
+}
+
+/// Noise is smooth, cloudy value noise, with a few octaves of finer detail added on top. It is
+/// good for breaking up a blend, like dust or rust over metal.
+pub struct Noise {
+    scale: f64,
+    octaves: u32
+}
+
+impl Noise {
+    /// Constructs noise whose largest features are about scale across, with octaves layers of
+    /// detail (each half the size of the last).
+    pub fn new(scale: f64, octaves: u32) -> Noise {
+        assert!(scale > 0.0, "Cannot make noise with features of no size!");
+        assert!(octaves > 0, "Cannot make noise with no octaves!");
+        Noise {scale: scale, octaves: octaves}
+    }
+}
+
+impl Texture for Noise {
+    fn value(&self, position: Vec3) -> f64 {
+        let mut sum = 0.0;
+        let mut amplitude = 1.0;
+        let mut total = 0.0;
+        let mut point = position * (1.0 / self.scale);
+
+        for _ in 0..self.octaves {
+            sum += value_noise(point) * amplitude;
+            total += amplitude;
+            amplitude *= 0.5;
+            point = point * 2.0;
+        }
+
+        sum / total
+    }
+}
+
+/// Smoothly interpolates random values picked at the corners of a grid of unit cubes.
+fn value_noise(point: Vec3) -> f64 {
+    let (x, y, z) = (point.x.floor(), point.y.floor(), point.z.floor());
+    let fade = |t: f64| t * t * (3.0 - 2.0 * t);
+    let (u, v, w) = (fade(point.x - x), fade(point.y - y), fade(point.z - z));
+    let (x, y, z) = (x as i64, y as i64, z as i64);
+
+    let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+    let corner = |dx: i64, dy: i64, dz: i64| hash(x + dx, y + dy, z + dz);
+
+    lerp(lerp(lerp(corner(0, 0, 0), corner(1, 0, 0), u),
+              lerp(corner(0, 1, 0), corner(1, 1, 0), u), v),
+         lerp(lerp(corner(0, 0, 1), corner(1, 0, 1), u),
+              lerp(corner(0, 1, 1), corner(1, 1, 1), u), v), w)
+}
+
+/// Returns a random looking (but fixed) number on [0, 1] for a grid corner.
+fn hash(x: i64, y: i64, z: i64) -> f64 {
+    let mut h = (x as u64).wrapping_mul(0x9e3779b97f4a7c15)
+                ^ (y as u64).wrapping_mul(0xc2b2ae3d27d4eb4f)
+                ^ (z as u64).wrapping_mul(0x165667b19e3779f9);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51afd7ed558ccd);
+    h ^= h >> 33;
+
+    (h >> 11) as f64 / (1u64 << 53) as f64
+}
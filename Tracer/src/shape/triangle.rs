@@ -0,0 +1,133 @@
+use ::Arc;
+use geom::Vec3;
+use geom::Ray;
+use geom::AABB;
+use geom::random::jitter;
+use shape::Shape;
+use shape::SurfaceSample;
+use shape::Intersection;
+use shape::Material;
+
+/// Determinants smaller than this are treated as a Ray running parallel to the Triangle.
+const EPSILON: f64 = 1.0e-9;
+
+/// Triangle is a flat, three-vertex shape. It is mostly useful as the building block of a
+/// TriangleMesh, but can be added to a World on its own.
+///
+/// A Triangle may optionally carry one normal per vertex, in which case the normal reported at
+/// an intersection is interpolated between them (smooth or "Phong" shading).
+#[derive(Clone)]
+pub struct Triangle {
+    vertices: [Vec3; 3],
+    normals: Option<[Vec3; 3]>,
+    material: Arc<Material>
+}
+
+impl Triangle {
+    /// Constructs a flat-shaded Triangle from three vertices, wound counter-clockwise when seen
+    /// from the front.
+    pub fn new(v0: Vec3, v1: Vec3, v2: Vec3, material: Arc<Material>) -> Triangle {
+        Triangle {vertices: [v0, v1, v2], normals: None, material: material}
+    }
+
+    /// Constructs a smooth-shaded Triangle from three vertices and their normals.
+    pub fn with_normals(v0: Vec3, v1: Vec3, v2: Vec3, n0: Vec3, n1: Vec3, n2: Vec3,
+                        material: Arc<Material>) -> Triangle {
+        Triangle {vertices: [v0, v1, v2], normals: Some([n0.norm(), n1.norm(), n2.norm()]),
+                  material: material}
+    }
+
+    /// Gets the three vertices of the Triangle.
+    pub fn get_vertices(&self) -> [Vec3; 3] {
+        self.vertices
+    }
+
+    /// Returns the area of the Triangle.
+    pub fn area(&self) -> f64 {
+        let v = self.vertices;
+        ((v[1] - v[0]) % (v[2] - v[0])).mag() / 2.0
+    }
+
+    /// Returns the geometric (face) normal of the Triangle.
+    pub fn face_normal(&self) -> Vec3 {
+        let v = self.vertices;
+        ((v[1] - v[0]) % (v[2] - v[0])).norm()
+    }
+}
+
+impl Shape for Triangle {
+    /// Returns the intersection of a Ray and the Triangle, or None if the Ray misses it.
+    fn intersect_first(&self, ray: Ray) -> Option<Intersection> {
+        let v0 = self.vertices[0];
+        let D = ray.direction;
+
+        // Moller-Trumbore: solve for the distance and the barycentric coordinates (u, v) at once.
+        let e1 = self.vertices[1] - v0;
+        let e2 = self.vertices[2] - v0;
+        let p = D % e2;
+        let det = e1 * p;
+
+        if det.abs() < EPSILON {
+            return None;
+        }
+
+        let inv_det = 1.0 / det;
+        let s = ray.origin - v0;
+        let u = (s * p) * inv_det;
+        if u < 0.0 || u > 1.0 {
+            return None;
+        }
+
+        let q = s % e1;
+        let v = (D * q) * inv_det;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let t = (e2 * q) * inv_det;
+        if t <= 0.0 {
+            return None;
+        }
+
+        let norm = match self.normals {
+            Some(n) => (n[0] * (1.0 - u - v) + n[1] * u + n[2] * v).norm(),
+            None => (e1 % e2).norm()
+        };
+
+        Some(Intersection::new(t, self.material.clone(), ray.origin + D * t, norm))
+    }
+
+    /// Returns a Vec containing the intersection of a Ray and the Triangle, if there is one.
+    fn intersect_all(&self, ray: Ray) -> Vec<Intersection> {
+        match self.intersect_first(ray) {
+            None => Vec::new(),
+            Some(intersection) => vec![intersection]
+        }
+    }
+
+    /// Returns the box enclosing the three vertices.
+    fn bounding_box(&self) -> Option<AABB> {
+        Some(AABB::from_points(&self.vertices))
+    }
+
+    /// Sets the material of the Triangle.
+    fn set_material(&mut self, material: Arc<Material>) {
+        self.material = material;
+    }
+
+    /// Gets the material of the Triangle.
+    fn get_material(&self) -> Arc<Material> {
+        self.material.clone()
+    }
+
+    /// Picks a point evenly over the Triangle.
+    fn sample_surface(&self) -> Option<SurfaceSample> {
+        // Folding a point in the unit square onto the triangle this way keeps it even.
+        let s = jitter().sqrt();
+        let (b0, b1) = (1.0 - s, jitter() * s);
+        let v = self.vertices;
+        let position = v[0] * b0 + v[1] * b1 + v[2] * (1.0 - b0 - b1);
+
+        Some(SurfaceSample {position: position, norm: self.face_normal(), pdf: 1.0 / self.area()})
+    }
+}